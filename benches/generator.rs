@@ -0,0 +1,112 @@
+//! Throughput of the generator on MB-scale documents.
+//!
+//! Run with `cargo bench`. Pass a number to change the size of the documents in MB,
+//! e.g. `cargo bench -- 50`.
+
+use std::hint::black_box;
+use std::io;
+use std::time::{Duration, Instant};
+
+use indexmap::IndexMap;
+use json_pretty::{Generator, GeneratorOptions, Layout, Node};
+
+// A log dump: an array of many small, flat records.
+fn records(bytes: usize) -> Node {
+    let mut records = Vec::new();
+    let mut size = 0;
+    for i in 0..usize::MAX {
+        if size >= bytes {
+            break;
+        }
+        let record = Node::Object(IndexMap::from([
+            ("id".to_string(), Node::Number(i.to_string())),
+            ("level".to_string(), Node::String("info".to_string())),
+            (
+                "message".to_string(),
+                Node::String(format!("request {} served in {} ms\n", i, i % 97)),
+            ),
+            ("ok".to_string(), Node::Boolean(i % 5 != 0)),
+            (
+                "tags".to_string(),
+                Node::Array(vec![Node::Null, Node::Null, Node::Null]),
+            ),
+        ]));
+        size += json_pretty::to_string(&record).len();
+        records.push(record);
+    }
+    Node::Array(records)
+}
+
+// A binary tree of objects, deep enough to be at least `bytes` long.
+fn nested(bytes: usize) -> Node {
+    fn tree(depth: usize) -> Node {
+        let mut kvm = IndexMap::new();
+        for key in ["alpha", "beta", "gamma"] {
+            kvm.insert(key.to_string(), Node::Number("3.14e10".to_string()));
+        }
+        if depth > 0 {
+            kvm.insert(
+                "children".to_string(),
+                Node::Array(vec![tree(depth - 1), tree(depth - 1)]),
+            );
+        }
+        Node::Object(kvm)
+    }
+
+    let mut depth = 0;
+    while (70 << depth) < bytes {
+        depth += 1;
+    }
+    tree(depth)
+}
+
+fn bench(name: &str, node: &Node, options: &GeneratorOptions) {
+    let generator = Generator::with_options(node, options);
+    let len = generator.generate().len();
+
+    let mut runs = 0;
+    let mut elapsed = Duration::ZERO;
+    while runs < 3 || elapsed < Duration::from_secs(2) {
+        let start = Instant::now();
+        generator.write_io(black_box(io::sink())).unwrap();
+        elapsed += start.elapsed();
+        runs += 1;
+    }
+
+    let per_run = elapsed / runs;
+    let throughput = len as f64 / per_run.as_secs_f64() / 1_000_000.0;
+    println!(
+        "{:<24} {:>8.1} MB {:>10.2?}/run {:>10.1} MB/s",
+        name,
+        len as f64 / 1_000_000.0,
+        per_run,
+        throughput
+    );
+}
+
+fn main() {
+    let megabytes = std::env::args()
+        .skip(1)
+        .find_map(|arg| arg.parse::<usize>().ok())
+        .unwrap_or(10);
+    let bytes = megabytes * 1_000_000;
+
+    let pretty = GeneratorOptions::default();
+    let minified = GeneratorOptions {
+        layout: Layout::Minified,
+        ..Default::default()
+    };
+    let inline = GeneratorOptions {
+        inline_arrays: Some(80),
+        ..Default::default()
+    };
+
+    let records = records(bytes);
+    bench("records/pretty", &records, &pretty);
+    bench("records/minified", &records, &minified);
+    bench("records/inline-arrays", &records, &inline);
+
+    let nested = nested(bytes);
+    bench("nested/pretty", &nested, &pretty);
+    bench("nested/minified", &nested, &minified);
+}
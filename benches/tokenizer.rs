@@ -0,0 +1,81 @@
+//! Throughput of the tokenizer and the parser on MB-scale JSON text.
+//!
+//! Run with `cargo bench`. Pass a number to change the size of the input in MB,
+//! e.g. `cargo bench -- 50`.
+
+use std::fmt::Write;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use json_pretty::Tokenizer;
+
+// A log dump with many short strings, some of which need unescaping.
+fn log_dump(bytes: usize) -> String {
+    let mut json = String::from("[");
+    let mut i = 0;
+    while json.len() < bytes {
+        if i > 0 {
+            json.push(',');
+        }
+        write!(
+            json,
+            r#"{{"id": {}, "level": "info", "message": "request {} served in {} ms{}", "latency": {}e-3, "ok": {}, "tags": [null, "café"]}}"#,
+            i,
+            i,
+            i % 97,
+            if i % 10 == 0 { "\\n" } else { "" },
+            i % 1000,
+            i % 5 != 0
+        )
+        .unwrap();
+        i += 1;
+    }
+    json.push(']');
+    json
+}
+
+fn bench<F: FnMut()>(name: &str, len: usize, mut run: F) {
+    let mut runs = 0;
+    let mut elapsed = Duration::ZERO;
+    while runs < 3 || elapsed < Duration::from_secs(2) {
+        let start = Instant::now();
+        run();
+        elapsed += start.elapsed();
+        runs += 1;
+    }
+
+    let per_run = elapsed / runs;
+    let throughput = len as f64 / per_run.as_secs_f64() / 1_000_000.0;
+    println!(
+        "{:<24} {:>8.1} MB {:>10.2?}/run {:>10.1} MB/s",
+        name,
+        len as f64 / 1_000_000.0,
+        per_run,
+        throughput
+    );
+}
+
+fn main() {
+    let megabytes = std::env::args()
+        .skip(1)
+        .find_map(|arg| arg.parse::<usize>().ok())
+        .unwrap_or(10);
+    let json = log_dump(megabytes * 1_000_000);
+
+    bench("tokenize/str", json.len(), || {
+        for token in Tokenizer::new(black_box(&json)) {
+            black_box(token.unwrap());
+        }
+    });
+    bench("tokenize/slice", json.len(), || {
+        for token in Tokenizer::from_slice(black_box(json.as_bytes())) {
+            black_box(token.unwrap());
+        }
+    });
+    bench("parse", json.len(), || {
+        black_box(json_pretty::parse(black_box(&json)).unwrap());
+    });
+    bench("parse_borrowed", json.len(), || {
+        black_box(json_pretty::parse_borrowed(black_box(&json)).unwrap());
+    });
+}
@@ -0,0 +1,266 @@
+//! A JSON tree which borrows its keys, strings and numbers from the source text wherever
+//! they contain no escapes.
+
+use std::borrow::Cow;
+use std::mem;
+use std::vec;
+
+use indexmap::IndexMap;
+
+use crate::parser;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Node<'a> {
+    Null,
+    Object(IndexMap<Cow<'a, str>, Node<'a>>),
+    /// See `parser::Node::MultiObject`.
+    MultiObject(Vec<(Cow<'a, str>, Node<'a>)>),
+    Array(Vec<Node<'a>>),
+    Boolean(bool),
+    Number(Cow<'a, str>),
+    String(Cow<'a, str>),
+}
+
+impl<'a> Node<'a> {
+    pub fn is_null(&self) -> bool {
+        matches!(self, Node::Null)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Node::Boolean(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    /// Returns the number as it is written in the source text.
+    pub fn as_number(&self) -> Option<&str> {
+        match self {
+            Node::Number(num) => Some(num),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        self.as_number()?.parse().ok()
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        self.as_number()?.parse().ok()
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Node::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Node<'a>]> {
+        match self {
+            Node::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&IndexMap<Cow<'a, str>, Node<'a>>> {
+        match self {
+            Node::Object(kvm) => Some(kvm),
+            _ => None,
+        }
+    }
+
+    pub fn as_multi_object(&self) -> Option<&[(Cow<'a, str>, Node<'a>)]> {
+        match self {
+            Node::MultiObject(members) => Some(members),
+            _ => None,
+        }
+    }
+
+    /// Returns the member named `key` if this is an object, or the first one of them.
+    pub fn get(&self, key: &str) -> Option<&Node<'a>> {
+        match self {
+            Node::MultiObject(members) => members
+                .iter()
+                .find_map(|(name, value)| (name == key).then_some(value)),
+            _ => self.as_object()?.get(key),
+        }
+    }
+
+    /// Returns the `index`-th element if this is an array.
+    pub fn get_index(&self, index: usize) -> Option<&Node<'a>> {
+        self.as_array()?.get(index)
+    }
+
+    /// Copies whatever is still borrowed from the source text into an owned tree.
+    pub fn into_owned(self) -> parser::Node {
+        // Containers are copied from a stack rather than by recursion, however deep they are.
+        let mut stack = Vec::new();
+        let mut copied = copy(self, &mut stack);
+        loop {
+            let copying = match stack.last_mut() {
+                Some(copying) => copying,
+                None => return copied.expect("a copied top-level value"),
+            };
+            if let Some(value) = copied.take() {
+                copying.push(value);
+            }
+            copied = match copying.next() {
+                Some(child) => copy(child, &mut stack),
+                None => stack.pop().map(Copying::finish),
+            };
+        }
+    }
+
+    /// Drops the tree without recursing, like `parser::Node::drop_deep`, which trees nested
+    /// thousands of levels deep need.
+    pub fn drop_deep(mut self) {
+        let mut stack = Vec::new();
+        take_children(&mut self, &mut stack);
+        while let Some(mut node) = stack.pop() {
+            take_children(&mut node, &mut stack);
+        }
+    }
+}
+
+fn take_children<'a>(node: &mut Node<'a>, stack: &mut Vec<Node<'a>>) {
+    match node {
+        Node::Object(kvm) => stack.extend(kvm.drain(..).map(|(_, value)| value)),
+        Node::MultiObject(members) => stack.extend(members.drain(..).map(|(_, value)| value)),
+        Node::Array(values) => stack.append(values),
+        _ => {}
+    }
+}
+
+// Copies a scalar, or starts copying a container on `stack` and returns nothing yet.
+fn copy<'a>(mut node: Node<'a>, stack: &mut Vec<Copying<'a>>) -> Option<parser::Node> {
+    let copying = match &mut node {
+        Node::Null => return Some(parser::Node::Null),
+        Node::Boolean(b) => return Some(parser::Node::Boolean(*b)),
+        Node::Number(num) => return Some(parser::Node::Number(mem::take(num).into_owned())),
+        Node::String(value) => return Some(parser::Node::String(mem::take(value).into_owned())),
+        Node::Object(kvm) => Copying::Object(
+            IndexMap::with_capacity(kvm.len()),
+            mem::take(kvm).into_iter(),
+            String::new(),
+        ),
+        Node::MultiObject(members) => Copying::MultiObject(
+            Vec::with_capacity(members.len()),
+            mem::take(members).into_iter(),
+            String::new(),
+        ),
+        Node::Array(values) => Copying::Array(
+            Vec::with_capacity(values.len()),
+            mem::take(values).into_iter(),
+        ),
+    };
+    stack.push(copying);
+    None
+}
+
+// A container being copied by `Node::into_owned`: the members copied so far, those still to
+// copy, and the key of the one being copied.
+enum Copying<'a> {
+    Object(
+        IndexMap<String, parser::Node>,
+        indexmap::map::IntoIter<Cow<'a, str>, Node<'a>>,
+        String,
+    ),
+    MultiObject(
+        Vec<(String, parser::Node)>,
+        vec::IntoIter<(Cow<'a, str>, Node<'a>)>,
+        String,
+    ),
+    Array(Vec<parser::Node>, vec::IntoIter<Node<'a>>),
+}
+
+impl<'a> Copying<'a> {
+    fn push(&mut self, value: parser::Node) {
+        match self {
+            Copying::Object(copied, _, key) => {
+                copied.insert(mem::take(key), value);
+            }
+            Copying::MultiObject(copied, _, key) => copied.push((mem::take(key), value)),
+            Copying::Array(copied, _) => copied.push(value),
+        }
+    }
+
+    fn next(&mut self) -> Option<Node<'a>> {
+        match self {
+            Copying::Object(_, rest, key) => rest.next().map(|(name, value)| {
+                *key = name.into_owned();
+                value
+            }),
+            Copying::MultiObject(_, rest, key) => rest.next().map(|(name, value)| {
+                *key = name.into_owned();
+                value
+            }),
+            Copying::Array(_, rest) => rest.next(),
+        }
+    }
+
+    fn finish(self) -> parser::Node {
+        match self {
+            Copying::Object(copied, ..) => parser::Node::Object(copied),
+            Copying::MultiObject(copied, ..) => parser::Node::MultiObject(copied),
+            Copying::Array(copied, _) => parser::Node::Array(copied),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_borrows_unescaped_values() {
+        let input = r#"{"plain": "text", "esc\"aped": ["line\nbreak", 1.5e3, true, null]}"#;
+        let node = crate::parse_borrowed(input).unwrap();
+
+        let kvm = node.as_object().unwrap();
+        assert!(matches!(
+            kvm.get_index(0),
+            Some((Cow::Borrowed("plain"), Node::String(Cow::Borrowed("text"))))
+        ));
+        assert!(matches!(kvm.get_index(1), Some((Cow::Owned(key), _)) if key == "esc\"aped"));
+
+        let values = node.get("esc\"aped").and_then(Node::as_array).unwrap();
+        assert!(matches!(&values[0], Node::String(Cow::Owned(value)) if value == "line\nbreak"));
+        assert!(matches!(&values[1], Node::Number(Cow::Borrowed("1.5e3"))));
+        assert_eq!(values[1].as_f64(), Some(1500.0));
+        assert_eq!(values[2].as_bool(), Some(true));
+        assert!(values[3].is_null());
+    }
+
+    #[test]
+    fn into_owned() {
+        let input = r#"{"a": ["b\n", 1, false, null, {}]}"#;
+        let node = crate::parse_borrowed(input).unwrap();
+
+        assert_eq!(node.into_owned(), crate::parse(input).unwrap());
+    }
+
+    #[test]
+    fn into_owned_deep_tree() {
+        let deep = || {
+            let mut node = Node::Null;
+            for _ in 0..200_000 {
+                node = Node::Array(vec![node]);
+            }
+            node
+        };
+
+        let owned = deep().into_owned();
+        let mut depth = 0;
+        let mut current = &owned;
+        while let Some(child) = current.get_index(0) {
+            depth += 1;
+            current = child;
+        }
+        assert_eq!(depth, 200_000);
+        assert!(current.is_null());
+        owned.drop_deep();
+
+        deep().drop_deep();
+    }
+}
@@ -0,0 +1,97 @@
+//! The comments of JSONC text, kept next to a tree so that they can be written back out.
+
+use std::collections::HashMap;
+
+/// A `//` or `/* */` comment as it is written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Comment {
+    pub text: String,
+    /// Whether the comment follows other tokens on the same line, rather than starting a line
+    /// of its own.
+    pub trailing: bool,
+}
+
+impl Comment {
+    /// Whether the comment runs up to the end of the line, i.e. is a `//` comment.
+    pub fn is_line(&self) -> bool {
+        self.text.starts_with("//")
+    }
+}
+
+/// The comments next to one value.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Attached {
+    /// The comments between the previous member or element, or the opening bracket, and this
+    /// value. For a member, they come before its key.
+    pub before: Vec<Comment>,
+    /// The comments on the line on which this value ends, and any after the top-level value.
+    pub after: Vec<Comment>,
+    /// For an object or an array, the comments between its last member or element and its
+    /// closing bracket.
+    pub end: Vec<Comment>,
+}
+
+/// The comments of a text, attached to values by their JSON Pointer (RFC 6901), such as
+/// `/a/0` for the first element of the member `a`. The top-level value is the empty pointer.
+/// Where an object keeps every member with the same key, see `DuplicateKeys::KeepAll`, the
+/// n-th repetition of a key is told apart by `~~n` after it, such as `/a~~1` for the second
+/// member `a`, which no pointer to a single value contains.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Comments {
+    values: HashMap<String, Attached>,
+}
+
+impl Comments {
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// The comments attached to the value at `pointer`.
+    pub fn get(&self, pointer: &str) -> Option<&Attached> {
+        self.values.get(pointer)
+    }
+
+    /// The comments attached to the value at `pointer`, which can be changed.
+    pub fn entry(&mut self, pointer: &str) -> &mut Attached {
+        self.values.entry(pointer.to_string()).or_default()
+    }
+}
+
+/// Appends a member name or an array index to a JSON Pointer.
+pub(crate) fn push_segment(pointer: &mut String, segment: &str) {
+    pointer.push('/');
+    for c in segment.chars() {
+        match c {
+            '~' => pointer.push_str("~0"),
+            '/' => pointer.push_str("~1"),
+            c => pointer.push(c),
+        }
+    }
+}
+
+/// Appends the `occurrence`-th member with the key `key` to a JSON Pointer, counting from 0.
+pub(crate) fn push_key(pointer: &mut String, key: &str, occurrence: usize) {
+    push_segment(pointer, key);
+    if occurrence > 0 {
+        pointer.push_str("~~");
+        pointer.push_str(&occurrence.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_pointers() {
+        let mut pointer = String::new();
+        push_segment(&mut pointer, "a/b");
+        push_segment(&mut pointer, "0");
+        push_segment(&mut pointer, "~x");
+        push_segment(&mut pointer, "");
+        push_key(&mut pointer, "a", 0);
+        push_key(&mut pointer, "a~", 2);
+
+        assert_eq!(pointer, "/a~1b/0/~0x//a/a~0~~2");
+    }
+}
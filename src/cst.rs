@@ -0,0 +1,804 @@
+//! A concrete syntax tree which keeps every token of a JSON text together with the whitespace
+//! and comments around it, so that it prints back byte for byte and can be edited in place.
+
+use std::borrow::Cow;
+use std::fmt;
+
+use crate::error::Error;
+use crate::generator::{Generator, GeneratorOptions, Layout};
+use crate::json_util;
+use crate::parser::{Node, ParserOptions};
+use crate::reader::Reader;
+use crate::span::Span;
+use crate::tokenizer::{SpannedToken, Token, TokenSource, Tokenizer, TokenizerOptions};
+use crate::visitor::{self, Visitor};
+
+/// A JSON text as it is written. `to_string` gives the text back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Document {
+    value: Value,
+    // The whitespace and comments after the top-level value.
+    end: String,
+}
+
+/// A value with the whitespace and comments before it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Value {
+    leading: String,
+    kind: Kind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Kind {
+    // A string, number, true, false or null as it is written.
+    Scalar(String),
+    Object(Object),
+    Array(Array),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Object {
+    items: Vec<Item>,
+    // The whitespace and comments before the closing bracket.
+    end: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Array {
+    items: Vec<Item>,
+    end: String,
+}
+
+// A member of an object or an element of an array, with the comma after it if there is one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Item {
+    key: Option<Key>,
+    value: Value,
+    // The whitespace and comments before the comma.
+    comma: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Key {
+    leading: String,
+    // The key as it is written, e.g. with its quotes and escapes.
+    text: String,
+    name: String,
+    // The whitespace and comments before the colon.
+    colon: String,
+}
+
+/// Parses `input` into a `Document`.
+pub fn parse(input: &str) -> Result<Document, Error> {
+    parse_with_options(input, TokenizerOptions::default(), ParserOptions::default())
+}
+
+/// Parses `input` into a `Document`, reading it as `parser::Parser` does with the same options.
+/// Warnings are not reported, and duplicate keys are all kept as they are.
+pub fn parse_with_options(
+    input: &str,
+    tokenizer_options: TokenizerOptions,
+    options: ParserOptions,
+) -> Result<Document, Error> {
+    let mut recorder = Recorder {
+        tokens: Tokenizer::with_options(input, tokenizer_options),
+        recorded: Vec::new(),
+    };
+    let options = ParserOptions {
+        recover: false,
+        ..options
+    };
+    let mut reader = Reader::from_tokens_with_options(&mut recorder, options);
+    while reader.next_event()?.is_some() {}
+
+    Ok(build(input, recorder.recorded))
+}
+
+// Hands the tokens on to the reader which checks them, and keeps all but the comments to build
+// the tree from.
+struct Recorder<'a> {
+    tokens: Tokenizer<'a>,
+    recorded: Vec<SpannedToken<'a>>,
+}
+
+impl<'a> TokenSource<'a> for Recorder<'a> {
+    fn next_spanned_token(&mut self) -> Result<SpannedToken<'a>, Error> {
+        let spanned = self.tokens.next_spanned_token()?;
+        if !matches!(spanned.token, Token::Comment(_)) {
+            self.recorded.push(spanned.clone());
+        }
+        Ok(spanned)
+    }
+}
+
+// An object or array whose closing bracket has not been reached yet.
+struct Open {
+    leading: String,
+    object: bool,
+    items: Vec<Item>,
+    key: Option<Key>,
+}
+
+// Builds the tree from tokens which the reader has accepted. Everything between two tokens
+// goes to the one after it.
+fn build(input: &str, tokens: Vec<SpannedToken>) -> Document {
+    let mut stack: Vec<Open> = Vec::new();
+    let mut value = None;
+    let mut offset = 0;
+
+    for SpannedToken { token, span } in tokens {
+        if token == Token::Eof {
+            break;
+        }
+        let expects_key = matches!(stack.last(), Some(open) if open.object && open.key.is_none());
+        let trivia = input[offset..span.start.offset].to_string();
+        let text = &input[span.start.offset..span.end.offset];
+        offset = span.end.offset;
+
+        let done = match token {
+            Token::LeftCurlyBranckt | Token::LeftSquareBrancket => {
+                stack.push(Open {
+                    leading: trivia,
+                    object: token == Token::LeftCurlyBranckt,
+                    items: Vec::new(),
+                    key: None,
+                });
+                continue;
+            }
+            Token::RightCurlyBranckt | Token::RightSquareBrancket => {
+                let open = stack.pop().expect("an open container");
+                let kind = if open.object {
+                    Kind::Object(Object {
+                        items: open.items,
+                        end: trivia,
+                    })
+                } else {
+                    Kind::Array(Array {
+                        items: open.items,
+                        end: trivia,
+                    })
+                };
+                Value {
+                    leading: open.leading,
+                    kind,
+                }
+            }
+            Token::Colon => {
+                let open = stack.last_mut().expect("an open object");
+                open.key.as_mut().expect("a key before the colon").colon = trivia;
+                continue;
+            }
+            Token::Comma => {
+                let open = stack.last_mut().expect("an open container");
+                open.items
+                    .last_mut()
+                    .expect("an item before the comma")
+                    .comma = Some(trivia);
+                continue;
+            }
+            token if expects_key => {
+                let name = match token {
+                    Token::String(name) | Token::Identifier(name) => name.into_owned(),
+                    // A reserved word in JSON5.
+                    _ => text.to_string(),
+                };
+                stack.last_mut().expect("an open object").key = Some(Key {
+                    leading: trivia,
+                    text: text.to_string(),
+                    name,
+                    colon: String::new(),
+                });
+                continue;
+            }
+            _ => Value {
+                leading: trivia,
+                kind: Kind::Scalar(text.to_string()),
+            },
+        };
+
+        match stack.last_mut() {
+            Some(open) => open.items.push(Item {
+                key: open.key.take(),
+                value: done,
+                comma: None,
+            }),
+            None => value = Some(done),
+        }
+    }
+
+    Document {
+        value: value.expect("a top-level value"),
+        end: input[offset..].to_string(),
+    }
+}
+
+impl Document {
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+
+    pub fn value_mut(&mut self) -> &mut Value {
+        &mut self.value
+    }
+
+    /// The value at a JSON Pointer (RFC 6901) such as `/a/0`, or the top-level value for the
+    /// empty pointer.
+    pub fn pointer(&self, pointer: &str) -> Option<&Value> {
+        let mut value = &self.value;
+        for segment in segments(pointer)? {
+            value = match &value.kind {
+                Kind::Object(object) => object.get(&segment)?,
+                Kind::Array(array) => array.get(segment.parse().ok()?)?,
+                Kind::Scalar(_) => return None,
+            };
+        }
+        Some(value)
+    }
+
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut Value> {
+        let mut value = &mut self.value;
+        for segment in segments(pointer)? {
+            value = match &mut value.kind {
+                Kind::Object(object) => object.get_mut(&segment)?,
+                Kind::Array(array) => array.get_mut(segment.parse().ok()?)?,
+                Kind::Scalar(_) => return None,
+            };
+        }
+        Some(value)
+    }
+}
+
+// Splits a JSON Pointer into the member names and array indices it is made of.
+fn segments(pointer: &str) -> Option<Vec<String>> {
+    if pointer.is_empty() {
+        return Some(Vec::new());
+    }
+    let segments = pointer.strip_prefix('/')?.split('/');
+    Some(
+        segments
+            .map(|segment| segment.replace("~1", "/").replace("~0", "~"))
+            .collect(),
+    )
+}
+
+impl fmt::Display for Document {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.value, self.end)
+    }
+}
+
+impl Value {
+    /// The string, number, true, false or null as it is written, e.g. `"a\n"` with its quotes
+    /// and escapes.
+    pub fn as_scalar(&self) -> Option<&str> {
+        match &self.kind {
+            Kind::Scalar(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&Object> {
+        match &self.kind {
+            Kind::Object(object) => Some(object),
+            _ => None,
+        }
+    }
+
+    pub fn as_object_mut(&mut self) -> Option<&mut Object> {
+        match &mut self.kind {
+            Kind::Object(object) => Some(object),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Array> {
+        match &self.kind {
+            Kind::Array(array) => Some(array),
+            _ => None,
+        }
+    }
+
+    pub fn as_array_mut(&mut self) -> Option<&mut Array> {
+        match &mut self.kind {
+            Kind::Array(array) => Some(array),
+            _ => None,
+        }
+    }
+
+    /// Replaces the value with `node`, written on a single line. The comments and whitespace
+    /// around it stay as they are. Fails with `Error::InvalidNumber`, leaving the value as it
+    /// is, if a number in `node` is not written as JSON allows.
+    pub fn set(&mut self, node: &Node) -> Result<(), Error> {
+        visitor::walk(node, &mut NumberCheck)?;
+        let options = GeneratorOptions {
+            layout: Layout::SingleLine,
+            ..Default::default()
+        };
+        let text = Generator::with_options(node, &options).generate();
+        let options = ParserOptions {
+            max_depth: usize::MAX,
+            ..Default::default()
+        };
+        let mut document = parse_with_options(&text, TokenizerOptions::default(), options)
+            .expect("generated JSON text to parse");
+        self.kind = std::mem::replace(&mut document.value.kind, Kind::Scalar(String::new()));
+        Ok(())
+    }
+
+    fn from_node(leading: String, node: &Node) -> Result<Self, Error> {
+        let mut value = Value {
+            leading,
+            kind: Kind::Scalar(String::new()),
+        };
+        value.set(node)?;
+        Ok(value)
+    }
+}
+
+// Rejects the numbers of a tree which are not JSON numbers. The generator writes numbers as
+// they are, so such a number would not read back as one value, if at all.
+struct NumberCheck;
+
+impl Visitor<'_> for NumberCheck {
+    type Error = Error;
+
+    fn number(&mut self, num: Cow<str>) -> Result<(), Error> {
+        // The generator writes these as null.
+        if matches!(num.as_ref(), "Infinity" | "-Infinity" | "NaN") {
+            return Ok(());
+        }
+        let mut tokenizer = Tokenizer::new(&num);
+        match (tokenizer.next_token(), tokenizer.next_token()) {
+            (Ok(Token::Number(_)), Ok(Token::Eof)) => Ok(()),
+            _ => Err(Error::InvalidNumber {
+                reason: format!(
+                    "{} is not a JSON number",
+                    json_util::quote(&num, &Default::default())
+                ),
+                span: Span::default(),
+            }),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // What is left to write, last first, so that nesting does not recurse.
+        let mut stack = vec![Piece::Value(self)];
+        while let Some(piece) = stack.pop() {
+            let value = match piece {
+                Piece::Text(text) => {
+                    f.write_str(text)?;
+                    continue;
+                }
+                Piece::Value(value) => value,
+            };
+            f.write_str(&value.leading)?;
+            let (open, items, end, close) = match &value.kind {
+                Kind::Scalar(text) => {
+                    f.write_str(text)?;
+                    continue;
+                }
+                Kind::Object(object) => ("{", &object.items, &object.end, "}"),
+                Kind::Array(array) => ("[", &array.items, &array.end, "]"),
+            };
+            f.write_str(open)?;
+            stack.extend([Piece::Text(close), Piece::Text(end)]);
+            for item in items.iter().rev() {
+                if let Some(comma) = &item.comma {
+                    stack.extend([Piece::Text(","), Piece::Text(comma)]);
+                }
+                stack.push(Piece::Value(&item.value));
+                if let Some(key) = &item.key {
+                    stack.extend([
+                        Piece::Text(":"),
+                        Piece::Text(&key.colon),
+                        Piece::Text(&key.text),
+                        Piece::Text(&key.leading),
+                    ]);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+enum Piece<'a> {
+    Text(&'a str),
+    Value(&'a Value),
+}
+
+// Like `parser::Node`, drops nested values from a stack on the heap rather than by recursion.
+impl Drop for Value {
+    fn drop(&mut self) {
+        let mut stack = Vec::new();
+        take_items(self, &mut stack);
+        while let Some(mut item) = stack.pop() {
+            take_items(&mut item.value, &mut stack);
+        }
+    }
+}
+
+fn take_items(value: &mut Value, stack: &mut Vec<Item>) {
+    match &mut value.kind {
+        Kind::Object(object) => stack.append(&mut object.items),
+        Kind::Array(array) => stack.append(&mut array.items),
+        Kind::Scalar(_) => {}
+    }
+}
+
+impl Object {
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// The names of the members, without quotes or escapes.
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.items.iter().map(|item| item.name())
+    }
+
+    /// The value of the member `key`, or of its last occurrence if it is duplicated.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        let item = self.items.iter().rev().find(|item| item.name() == key)?;
+        Some(&item.value)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
+        let item = self
+            .items
+            .iter_mut()
+            .rev()
+            .find(|item| item.name() == key)?;
+        Some(&mut item.value)
+    }
+
+    /// Sets the member `key` to `node`. A new member is added after the last one and laid out
+    /// like it. Fails like `Value::set`.
+    pub fn insert(&mut self, key: &str, node: &Node) -> Result<(), Error> {
+        if let Some(value) = self.get_mut(key) {
+            return value.set(node);
+        }
+
+        let (leading, colon, value) = match self.items.last() {
+            Some(Item {
+                key: Some(last),
+                value,
+                ..
+            }) => (
+                indentation(&last.leading),
+                whitespace(&last.colon).to_string(),
+                whitespace(&value.leading).to_string(),
+            ),
+            _ => (String::new(), String::new(), " ".to_string()),
+        };
+        let item = Item {
+            key: Some(Key {
+                leading,
+                text: json_util::quote(key, &Default::default()),
+                name: key.to_string(),
+                colon,
+            }),
+            value: Value::from_node(value, node)?,
+            comma: None,
+        };
+        append(&mut self.items, &mut self.end, item);
+        Ok(())
+    }
+
+    /// Removes the member `key`, or its last occurrence if it is duplicated, together with the
+    /// comments before it and on its line.
+    pub fn remove(&mut self, key: &str) -> Option<Value> {
+        let index = self.items.iter().rposition(|item| item.name() == key)?;
+        Some(remove(&mut self.items, &mut self.end, index))
+    }
+}
+
+impl Array {
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Value> {
+        self.items.get(index).map(|item| &item.value)
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut Value> {
+        self.items.get_mut(index).map(|item| &mut item.value)
+    }
+
+    /// Adds `node` after the last element, laid out like it. Fails like `Value::set`.
+    pub fn push(&mut self, node: &Node) -> Result<(), Error> {
+        let leading = match self.items.last() {
+            Some(last) => indentation(&last.value.leading),
+            None => String::new(),
+        };
+        let item = Item {
+            key: None,
+            value: Value::from_node(leading, node)?,
+            comma: None,
+        };
+        append(&mut self.items, &mut self.end, item);
+        Ok(())
+    }
+
+    /// Removes the element at `index` together with the comments before it and on its line.
+    pub fn remove(&mut self, index: usize) -> Option<Value> {
+        if index >= self.items.len() {
+            return None;
+        }
+        Some(remove(&mut self.items, &mut self.end, index))
+    }
+}
+
+impl Item {
+    fn name(&self) -> &str {
+        self.key.as_ref().map_or("", |key| &key.name)
+    }
+
+    // The whitespace and comments before the key of a member or before an element.
+    fn leading_mut(&mut self) -> &mut String {
+        match &mut self.key {
+            Some(key) => &mut key.leading,
+            None => &mut self.value.leading,
+        }
+    }
+}
+
+// Adds `item` after the last one. The comments on the line of the last item stay on it.
+fn append(items: &mut Vec<Item>, end: &mut String, mut item: Item) {
+    if let Some(last) = items.last_mut() {
+        match last.comma {
+            // Keeps the trailing comma after the new last item.
+            Some(_) => item.comma = Some(String::new()),
+            None => last.comma = Some(String::new()),
+        }
+        let line = end.rfind('\n').map_or(0, |i| line_break_start(end, i));
+        item.leading_mut().insert_str(0, &end[..line]);
+        end.replace_range(..line, "");
+    }
+    items.push(item);
+}
+
+// Removes the item at `index`. The comments on its line go with it, while those on the line
+// before it stay there.
+fn remove(items: &mut Vec<Item>, end: &mut String, index: usize) -> Value {
+    let mut item = items.remove(index);
+    let comma = item.comma.take();
+    let leading = std::mem::take(item.leading_mut());
+    let following = if index < items.len() {
+        items[index].leading_mut()
+    } else {
+        // The new last item takes over the trailing comma, if any.
+        if let Some(last) = items.last_mut() {
+            last.comma = comma;
+        }
+        end
+    };
+
+    match following.find('\n') {
+        Some(i) => {
+            let kept = leading
+                .find('\n')
+                .map_or(0, |j| line_break_start(&leading, j));
+            following.replace_range(..line_break_start(following, i), &leading[..kept]);
+        }
+        // The new first item starts where the removed one did.
+        None if index == 0 => *following = whitespace(&leading).to_string(),
+        None => {}
+    }
+    item.value
+}
+
+// The whitespace which starts an item after one starting with `leading`, without its comments:
+// a line break and the indentation after it, or a space on the same line.
+fn indentation(leading: &str) -> String {
+    match leading.rfind('\n') {
+        Some(i) => {
+            let rest = &leading[i + 1..];
+            let indent = rest.len() - rest.trim_start_matches([' ', '\t']).len();
+            leading[line_break_start(leading, i)..i + 1 + indent].to_string()
+        }
+        None => " ".to_string(),
+    }
+}
+
+// The whitespace at the start of `text`.
+fn whitespace(text: &str) -> &str {
+    &text[..text.len() - text.trim_start().len()]
+}
+
+// Where the line break ending at the '\n' at `i` starts, i.e. at a preceding '\r' if any.
+fn line_break_start(text: &str, i: usize) -> usize {
+    if text[..i].ends_with('\r') {
+        i - 1
+    } else {
+        i
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_jsonc(input: &str) -> Result<Document, Error> {
+        let options = TokenizerOptions {
+            comments: true,
+            ..Default::default()
+        };
+        parse_with_options(input, options, ParserOptions::default())
+    }
+
+    #[test]
+    fn print_unchanged() {
+        let inputs = [
+            "{\"a\": [1, 2.5e3, \"x\\n\"], \"b\" : {\"c\":null}}",
+            "\u{feff} \r\n[ true ,false,\r\n\t{} ]\r\n",
+            "  \"top\"  ",
+            "[]",
+            "{\n    \"a\": 1, // one\n    /* b */ \"b\": [\n    ]\n}\n// end\n",
+        ];
+        let jsonc = TokenizerOptions {
+            comments: true,
+            json5: true,
+            ..Default::default()
+        };
+        for input in inputs {
+            let document = parse_with_options(input, jsonc, ParserOptions::default());
+            assert_eq!(
+                document.map(|document| document.to_string()),
+                Ok(input.to_string())
+            );
+        }
+
+        let json5 = "// JSON5\n{unquoted: 'single', hex: 0x1F, null: [1,],}";
+        let options = ParserOptions {
+            json5: true,
+            ..Default::default()
+        };
+        let document = parse_with_options(json5, jsonc, options).unwrap();
+        assert_eq!(document.to_string(), json5);
+        assert_eq!(
+            document.pointer("/hex").and_then(Value::as_scalar),
+            Some("0x1F")
+        );
+        assert_eq!(
+            document.pointer("/null/0").and_then(Value::as_scalar),
+            Some("1")
+        );
+
+        assert!(parse("[1,]").is_err());
+        assert!(parse("// a\n1").is_err());
+    }
+
+    #[test]
+    fn print_deep_tree() {
+        let input = format!("{}1{}", "[ ".repeat(200_000), " ]".repeat(200_000));
+        let options = ParserOptions {
+            max_depth: usize::MAX,
+            ..Default::default()
+        };
+        let document = parse_with_options(&input, Default::default(), options).unwrap();
+
+        assert_eq!(document.to_string(), input);
+    }
+
+    #[test]
+    fn find_values() {
+        let document = parse(r#"{"a/b": [1, {"~c": 2}], "d": 3, "d": 4}"#).unwrap();
+
+        assert_eq!(
+            document.pointer("/a~1b/1/~0c").and_then(Value::as_scalar),
+            Some("2")
+        );
+        assert_eq!(document.pointer("/d").and_then(Value::as_scalar), Some("4"));
+        assert_eq!(
+            document.pointer("").map(|value| value.to_string()),
+            Some(document.to_string())
+        );
+        assert!(document.pointer("/a~1b/2").is_none());
+        assert!(document.pointer("/d/0").is_none());
+        assert!(document.pointer("a").is_none());
+        assert_eq!(
+            document
+                .value()
+                .as_object()
+                .map(|object| object.keys().collect::<Vec<_>>()),
+            Some(vec!["a/b", "d", "d"])
+        );
+    }
+
+    #[test]
+    fn set_values() {
+        let input = "{\n  \"version\": \"1.0\", // bump me\n  \"deps\": [\"a\"]\n}\n";
+        let mut document = parse_jsonc(input).unwrap();
+
+        let version = document.pointer_mut("/version").unwrap();
+        version.set(&Node::String("1.1".to_string())).unwrap();
+        document
+            .pointer_mut("/deps")
+            .unwrap()
+            .set(&Node::Array(vec![Node::Null, Node::Boolean(true)]))
+            .unwrap();
+
+        assert_eq!(
+            document.to_string(),
+            "{\n  \"version\": \"1.1\", // bump me\n  \"deps\": [null, true]\n}\n"
+        );
+        assert_eq!(
+            document.pointer("/deps/1").and_then(Value::as_scalar),
+            Some("true")
+        );
+    }
+
+    #[test]
+    fn insert_and_remove() {
+        let number = |n: &str| Node::Number(n.to_string());
+        let input = "{\n    \"a\": 1, // one\n    \"b\": 2 // two\n}";
+        let mut document = parse_jsonc(input).unwrap();
+        let object = document.value_mut().as_object_mut().unwrap();
+
+        object.insert("c\"", &number("3")).unwrap();
+        assert_eq!(
+            document.to_string(),
+            "{\n    \"a\": 1, // one\n    \"b\": 2, // two\n    \"c\\\"\": 3\n}"
+        );
+
+        let object = document.value_mut().as_object_mut().unwrap();
+        let removed = object.remove("c\"").unwrap();
+        assert_eq!(removed.as_scalar(), Some("3"));
+        assert!(object.remove("x").is_none());
+        assert_eq!(document.to_string(), input);
+
+        let object = document.value_mut().as_object_mut().unwrap();
+        object.remove("a");
+        assert_eq!(document.to_string(), "{\n    \"b\": 2 // two\n}");
+
+        let options = ParserOptions {
+            trailing_commas: true,
+            ..Default::default()
+        };
+        let mut document = parse_with_options("[1, 2,]", Default::default(), options).unwrap();
+        let array = document.value_mut().as_array_mut().unwrap();
+        array.push(&number("3")).unwrap();
+        array.remove(0);
+        assert_eq!(document.to_string(), "[2, 3,]");
+
+        let mut document = parse("{}").unwrap();
+        let object = document.value_mut().as_object_mut().unwrap();
+        object.insert("a", &Node::Array(Vec::new())).unwrap();
+        object.insert("b", &Node::Null).unwrap();
+        assert_eq!(document.to_string(), "{\"a\": [], \"b\": null}");
+    }
+
+    #[test]
+    fn reject_invalid_numbers() {
+        let input = "{\"a\": [1]}";
+        let mut document = parse(input).unwrap();
+        let number = |n: &str| Node::Number(n.to_string());
+
+        for num in ["abc", "", "1, 2", "01", "1 2"] {
+            let value = document.pointer_mut("/a").unwrap();
+            assert!(matches!(
+                value.set(&Node::Array(vec![number(num)])),
+                Err(Error::InvalidNumber { .. })
+            ));
+            let object = document.value_mut().as_object_mut().unwrap();
+            assert!(object.insert("b", &number(num)).is_err());
+            let array = document.pointer_mut("/a").and_then(Value::as_array_mut);
+            assert!(array.unwrap().push(&number(num)).is_err());
+        }
+        assert_eq!(document.to_string(), input);
+
+        let value = document.pointer_mut("/a/0").unwrap();
+        value.set(&number("-1.5e3")).unwrap();
+        assert_eq!(document.to_string(), "{\"a\": [-1.5e3]}");
+    }
+}
@@ -0,0 +1,305 @@
+use std::fmt::Write;
+use std::ops::Range;
+
+const CONTEXT: usize = 3;
+
+// How far into the edit script between two texts the search for its middle goes before it
+// gives up and replaces all the lines in between, so that texts which have little in common,
+// such as minified and pretty-printed JSON, are compared in bounded time.
+const MAX_EDITS: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edit {
+    Equal(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+/// Returns the unified diff between `old` and `new`, or an empty string when they are equal.
+pub fn unified_diff(name: &str, old: &str, new: &str) -> String {
+    let old = old.split_inclusive('\n').collect::<Vec<_>>();
+    let new = new.split_inclusive('\n').collect::<Vec<_>>();
+    let edits = diff_lines(&old, &new);
+
+    let mut out = String::new();
+    for (start, end) in hunks(&edits) {
+        if out.is_empty() {
+            writeln!(out, "--- a/{}", name).unwrap();
+            writeln!(out, "+++ b/{}", name).unwrap();
+        }
+
+        let old_lines = edits[start..end]
+            .iter()
+            .filter(|edit| !matches!(edit, Edit::Insert(_)))
+            .count();
+        let new_lines = edits[start..end]
+            .iter()
+            .filter(|edit| !matches!(edit, Edit::Delete(_)))
+            .count();
+        let (old_start, new_start) = line_numbers(&edits[..start]);
+        writeln!(
+            out,
+            "@@ -{} +{} @@",
+            range(old_start, old_lines),
+            range(new_start, new_lines)
+        )
+        .unwrap();
+
+        for edit in &edits[start..end] {
+            let (prefix, line) = match *edit {
+                Edit::Equal(i, _) => (' ', old[i]),
+                Edit::Delete(i) => ('-', old[i]),
+                Edit::Insert(j) => ('+', new[j]),
+            };
+            out.push(prefix);
+            out.push_str(line);
+            if !line.ends_with('\n') {
+                out.push_str("\n\\ No newline at end of file\n");
+            }
+        }
+    }
+
+    out
+}
+
+// Formats a hunk range like GNU diff does; an empty range refers to the line before it.
+fn range(start: usize, len: usize) -> String {
+    match len {
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, len),
+    }
+}
+
+// Counts the lines of the old and new text which precede the given edits.
+fn line_numbers(edits: &[Edit]) -> (usize, usize) {
+    edits.iter().fold((0, 0), |(old, new), edit| match edit {
+        Edit::Equal(..) => (old + 1, new + 1),
+        Edit::Delete(_) => (old + 1, new),
+        Edit::Insert(_) => (old, new + 1),
+    })
+}
+
+// Groups changed edits with their surrounding context into ranges of `edits`.
+fn hunks(edits: &[Edit]) -> Vec<(usize, usize)> {
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+
+    for (i, edit) in edits.iter().enumerate() {
+        if matches!(edit, Edit::Equal(..)) {
+            continue;
+        }
+
+        let start = i.saturating_sub(CONTEXT);
+        let end = (i + 1 + CONTEXT).min(edits.len());
+        match hunks.last_mut() {
+            Some(last) if last.1 >= start => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    hunks
+}
+
+// Myers' difference algorithm in linear space: the middle of a shortest edit script splits
+// both texts, and the parts before and after it are compared in turn.
+fn diff_lines(old: &[&str], new: &[&str]) -> Vec<Edit> {
+    let limit = ((old.len() + new.len()).div_ceil(2) + 1).min(MAX_EDITS);
+    let mut myers = Myers {
+        old,
+        new,
+        forward: vec![0; 2 * limit + 1],
+        backward: vec![0; 2 * limit + 1],
+        limit,
+        edits: Vec::new(),
+    };
+    myers.diff(0..old.len(), 0..new.len());
+    myers.edits
+}
+
+struct Myers<'a> {
+    old: &'a [&'a str],
+    new: &'a [&'a str],
+    // The furthest line of `old` reached on each diagonal, indexed by the diagonal plus
+    // `limit`, by the searches from the start and from the end.
+    forward: Vec<isize>,
+    backward: Vec<isize>,
+    limit: usize,
+    edits: Vec<Edit>,
+}
+
+impl Myers<'_> {
+    fn diff(&mut self, mut old: Range<usize>, mut new: Range<usize>) {
+        while !old.is_empty() && !new.is_empty() && self.old[old.start] == self.new[new.start] {
+            self.edits.push(Edit::Equal(old.start, new.start));
+            old.start += 1;
+            new.start += 1;
+        }
+        let mut suffix = 0;
+        while suffix < old.len().min(new.len())
+            && self.old[old.end - suffix - 1] == self.new[new.end - suffix - 1]
+        {
+            suffix += 1;
+        }
+        old.end -= suffix;
+        new.end -= suffix;
+
+        let middle = if old.is_empty() || new.is_empty() {
+            None
+        } else {
+            self.middle_snake(old.clone(), new.clone())
+        };
+        match middle {
+            Some((x, y)) => {
+                self.diff(old.start..x, new.start..y);
+                self.diff(x..old.end, y..new.end);
+            }
+            None => {
+                self.edits.extend(old.clone().map(Edit::Delete));
+                self.edits.extend(new.clone().map(Edit::Insert));
+            }
+        }
+        self.edits
+            .extend((0..suffix).map(|i| Edit::Equal(old.end + i, new.end + i)));
+    }
+
+    // Returns a point halfway through a shortest edit script from `old` to `new`, which
+    // differ in their first and last lines, or None if it is further than `limit` edits in.
+    fn middle_snake(&mut self, old: Range<usize>, new: Range<usize>) -> Option<(usize, usize)> {
+        let n = old.len() as isize;
+        let m = new.len() as isize;
+        let delta = n - m;
+        let odd = delta % 2 != 0;
+        let offset = self.limit as isize;
+        let at = move |k: isize| (k + offset) as usize;
+
+        self.forward[at(1)] = 0;
+        self.backward[at(1)] = 0;
+        for d in 0..offset {
+            for k in (-d..=d).step_by(2) {
+                let v = &self.forward;
+                let mut x = if k == -d || (k != d && v[at(k - 1)] < v[at(k + 1)]) {
+                    v[at(k + 1)]
+                } else {
+                    v[at(k - 1)] + 1
+                };
+                let (start_x, start_y) = (x, x - k);
+                let mut y = start_y;
+                while x < n
+                    && y < m
+                    && self.old[old.start + x as usize] == self.new[new.start + y as usize]
+                {
+                    x += 1;
+                    y += 1;
+                }
+                self.forward[at(k)] = x;
+
+                if odd && (k - delta).abs() < d && x + self.backward[at(delta - k)] >= n {
+                    return Some((old.start + start_x as usize, new.start + start_y as usize));
+                }
+            }
+
+            for k in (-d..=d).step_by(2) {
+                let v = &self.backward;
+                let mut x = if k == -d || (k != d && v[at(k - 1)] < v[at(k + 1)]) {
+                    v[at(k + 1)]
+                } else {
+                    v[at(k - 1)] + 1
+                };
+                let mut y = x - k;
+                while x < n
+                    && y < m
+                    && self.old[old.end - 1 - x as usize] == self.new[new.end - 1 - y as usize]
+                {
+                    x += 1;
+                    y += 1;
+                }
+                self.backward[at(k)] = x;
+
+                if !odd && (k - delta).abs() <= d && x + self.forward[at(delta - k)] >= n {
+                    return Some((old.end - x as usize, new.end - y as usize));
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_equal() {
+        assert_eq!(unified_diff("a.json", "[\n]\n", "[\n]\n"), "");
+        assert_eq!(unified_diff("a.json", "", ""), "");
+    }
+
+    #[test]
+    fn diff_change() {
+        let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n";
+        let new = "1\n2\n3\n4\nfive\n6\n7\n8\n9\n";
+
+        assert_eq!(
+            unified_diff("a.json", old, new),
+            "--- a/a.json\n+++ b/a.json\n@@ -2,7 +2,7 @@\n 2\n 3\n 4\n-5\n+five\n 6\n 7\n 8\n"
+        );
+    }
+
+    #[test]
+    fn diff_separate_hunks() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+        let new = "A\nb\nc\nd\ne\nf\ng\nh\ni\nJ\n";
+
+        assert_eq!(
+            unified_diff("a.json", old, new),
+            concat!(
+                "--- a/a.json\n+++ b/a.json\n",
+                "@@ -1,4 +1,4 @@\n-a\n+A\n b\n c\n d\n",
+                "@@ -7,4 +7,4 @@\n g\n h\n i\n-j\n+J\n",
+            )
+        );
+    }
+
+    #[test]
+    fn diff_missing_newline() {
+        assert_eq!(
+            unified_diff("a.json", "{\"a\":1}", "{\n    \"a\": 1\n}\n"),
+            concat!(
+                "--- a/a.json\n+++ b/a.json\n@@ -1 +1,3 @@\n",
+                "-{\"a\":1}\n\\ No newline at end of file\n",
+                "+{\n+    \"a\": 1\n+}\n",
+            )
+        );
+    }
+
+    #[test]
+    fn diff_long_texts() {
+        let lines = (0..100_000).map(|i| format!("{}\n", i)).collect::<String>();
+        let changed = lines.replacen("50000\n", "fifty thousand\n", 1);
+
+        assert_eq!(
+            unified_diff("a.json", &lines, &changed),
+            concat!(
+                "--- a/a.json\n+++ b/a.json\n@@ -49998,7 +49998,7 @@\n",
+                " 49997\n 49998\n 49999\n-50000\n+fifty thousand\n 50001\n 50002\n 50003\n",
+            )
+        );
+
+        // Too different to be compared line by line in time, so all of it is replaced.
+        let old = (0..5000).map(|i| format!("{}\n", i)).collect::<String>();
+        let new = (0..5000)
+            .map(|i| format!("{}\n", 2 * i + 1))
+            .collect::<String>();
+        let diff = unified_diff("a.json", &old, &new);
+        assert!(diff.starts_with("--- a/a.json\n+++ b/a.json\n@@ -1,5000 +1,5000 @@\n-0\n-1\n"));
+        assert_eq!(diff.lines().count(), 10_003);
+    }
+
+    #[test]
+    fn diff_from_empty() {
+        assert_eq!(
+            unified_diff("a.json", "", "1\n"),
+            "--- a/a.json\n+++ b/a.json\n@@ -0,0 +1 @@\n+1\n"
+        );
+    }
+}
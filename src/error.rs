@@ -0,0 +1,181 @@
+use std::fmt;
+use std::io;
+
+use crate::json_util;
+use crate::span::{Position, Span};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    UnexpectedChar {
+        found: char,
+        span: Span,
+    },
+    UnexpectedToken {
+        expected: String,
+        found: String,
+        span: Span,
+    },
+    UnterminatedString {
+        span: Span,
+    },
+    InvalidEscape {
+        reason: String,
+        span: Span,
+    },
+    InvalidNumber {
+        reason: String,
+        span: Span,
+    },
+    TrailingContent {
+        found: String,
+        span: Span,
+    },
+    DepthLimitExceeded {
+        limit: usize,
+        span: Span,
+    },
+    UnexpectedEof {
+        expected: String,
+        span: Span,
+    },
+    InvalidUtf8 {
+        span: Span,
+    },
+    /// A key appears twice in the same object; `span` points at the second occurrence.
+    DuplicateKey {
+        key: String,
+        first: Span,
+        span: Span,
+    },
+    /// A comma after the last member or element, accepted with `ParserOptions::trailing_commas`.
+    TrailingComma {
+        span: Span,
+    },
+    /// `Infinity`, `-Infinity` or `NaN` in JSON5, which JSON cannot represent and the generator
+    /// writes as null.
+    NonFiniteNumber {
+        found: String,
+        span: Span,
+    },
+    /// Reading the input or writing the output failed.
+    Io {
+        kind: io::ErrorKind,
+        message: String,
+        span: Span,
+    },
+}
+
+impl Error {
+    pub(crate) fn io(err: io::Error, position: Position) -> Self {
+        Error::Io {
+            kind: err.kind(),
+            message: err.to_string(),
+            span: Span::new(position, position),
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            Error::UnexpectedChar { span, .. }
+            | Error::UnexpectedToken { span, .. }
+            | Error::UnterminatedString { span }
+            | Error::InvalidEscape { span, .. }
+            | Error::InvalidNumber { span, .. }
+            | Error::TrailingContent { span, .. }
+            | Error::DepthLimitExceeded { span, .. }
+            | Error::UnexpectedEof { span, .. }
+            | Error::InvalidUtf8 { span }
+            | Error::DuplicateKey { span, .. }
+            | Error::TrailingComma { span }
+            | Error::NonFiniteNumber { span, .. }
+            | Error::Io { span, .. } => *span,
+        }
+    }
+
+    /// The description of the error without its position.
+    pub fn message(&self) -> String {
+        match self {
+            Error::UnexpectedChar { found, .. } => format!(
+                "The tokenizer found an unexpected character '{}'.",
+                found.escape_debug()
+            ),
+            Error::UnexpectedToken {
+                expected, found, ..
+            } => format!("Expected {}, but found {}.", expected, found),
+            Error::UnterminatedString { .. } => {
+                "The tokenizer reached EOF before finding \" which represents the end of a string."
+                    .to_string()
+            }
+            Error::InvalidEscape { reason, .. } => format!("Invalid escape sequence: {}.", reason),
+            Error::InvalidNumber { reason, .. } => format!("Invalid number: {}.", reason),
+            Error::TrailingContent { found, .. } => format!(
+                "Found {} after the end of the JSON text; only whitespace may follow the top-level value.",
+                found
+            ),
+            Error::DepthLimitExceeded { limit, .. } => {
+                format!("The nesting depth exceeds the limit of {}.", limit)
+            }
+            Error::UnexpectedEof { expected, .. } => {
+                format!("Expected {}, but reached EOF.", expected)
+            }
+            Error::InvalidUtf8 { .. } => "The input is not valid UTF-8.".to_string(),
+            Error::DuplicateKey { key, first, .. } => format!(
+                "The key {} is duplicated; it first appears at line {}, column {}.",
+                json_util::quote(key, &Default::default()),
+                first.start.line,
+                first.start.column
+            ),
+            Error::TrailingComma { .. } => {
+                "Found a comma after the last member or element.".to_string()
+            }
+            Error::NonFiniteNumber { found, .. } => {
+                format!("{} cannot be written as JSON and becomes null.", found)
+            }
+            Error::Io { message, .. } => format!("I/O error: {}.", message),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let start = self.span().start;
+        write!(
+            f,
+            "{} (line {}, column {})",
+            self.message(),
+            start.line,
+            start.column
+        )
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// A problem found in the input which did not necessarily stop the parser.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub error: Error,
+}
+
+impl Diagnostic {
+    pub fn warning(error: Error) -> Self {
+        Self {
+            severity: Severity::Warning,
+            error,
+        }
+    }
+
+    pub fn error(error: Error) -> Self {
+        Self {
+            severity: Severity::Error,
+            error,
+        }
+    }
+}
@@ -1,102 +1,500 @@
-use indexmap::IndexMap;
+use std::borrow::Cow;
+use std::fmt;
+use std::io;
 
+use crate::comments::Comments;
+use crate::error::Error;
+use crate::json_util::{self, EscapeOptions};
 use crate::parser::Node;
+use crate::span::Position;
+use crate::visitor::{self, Visitor};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    /// One member or element per line, indented by nesting depth.
+    #[default]
+    Pretty,
+    /// Everything on one line with a space after each `:` and `,`.
+    SingleLine,
+    /// Everything on one line without any whitespace.
+    Minified,
+}
 
-pub struct Generator {
-    node: Node,
-    indent_size: usize,
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    CrLf,
 }
 
-impl Generator {
-    pub fn new(node: Node, indent_size: usize) -> Self {
-        Self { node, indent_size }
+impl LineEnding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GeneratorOptions {
+    /// The string written once per nesting level, e.g. four spaces or a tab.
+    pub indent: String,
+    pub layout: Layout,
+    pub line_ending: LineEnding,
+    /// Whether the output ends with a line ending.
+    pub final_newline: bool,
+    pub space_before_colon: bool,
+    pub space_after_colon: bool,
+    /// Keep an array of scalars on a single line, e.g. `[1, 2, 3]`, when that is at most
+    /// this many characters long.
+    pub inline_arrays: Option<usize>,
+    /// Write a comma after the last member or element of each object and array which spans
+    /// several lines, as JSON5 and JavaScript allow. The output is then no longer JSON.
+    pub trailing_commas: bool,
+    pub escape: EscapeOptions,
+}
+
+impl GeneratorOptions {
+    pub fn with_indent_size(indent_size: usize) -> Self {
+        Self {
+            indent: " ".repeat(indent_size),
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for GeneratorOptions {
+    fn default() -> Self {
+        Self {
+            indent: " ".repeat(4),
+            layout: Layout::default(),
+            line_ending: LineEnding::default(),
+            final_newline: false,
+            space_before_colon: false,
+            space_after_colon: true,
+            inline_arrays: None,
+            trailing_commas: false,
+            escape: EscapeOptions::default(),
+        }
+    }
+}
+
+pub struct Generator<'a> {
+    node: &'a Node,
+    comments: Option<&'a Comments>,
+    options: GeneratorOptions,
+}
+
+impl<'a> Generator<'a> {
+    pub fn new(node: &'a Node, indent_size: usize) -> Self {
+        Self::with_options(node, &GeneratorOptions::with_indent_size(indent_size))
+    }
+
+    pub fn with_options(node: &'a Node, options: &GeneratorOptions) -> Self {
+        Self {
+            node,
+            comments: None,
+            options: options.clone(),
+        }
+    }
+
+    /// Writes `comments` next to the values to which they are attached. Comments are only
+    /// written in the `Pretty` layout.
+    pub fn with_comments(mut self, comments: &'a Comments) -> Self {
+        self.comments = Some(comments);
+        self
     }
 
     pub fn generate(&self) -> String {
-        self.generate_impl(&self.node, "")
+        let mut json = String::new();
+        self.write_to(&mut json)
+            .expect("writing to a String cannot fail");
+        json
+    }
+
+    /// Writes the JSON text into `out` in a single pass, without building it in memory first.
+    pub fn write_to<W: fmt::Write>(&self, out: &mut W) -> fmt::Result {
+        let mut emitter = Emitter::new(out, &self.options);
+        match self.comments {
+            Some(comments) => visitor::walk_with_comments(self.node, comments, &mut emitter),
+            None => visitor::walk(self.node, &mut emitter),
+        }
+        .and_then(|()| emitter.finish())
+        .map_err(|_| fmt::Error)
+    }
+
+    /// Writes the JSON text into `out`. Wrap unbuffered sinks such as files or stdout in a
+    /// `BufWriter`, since the text is written in many small pieces.
+    pub fn write_io<W: io::Write>(&self, out: W) -> io::Result<()> {
+        let mut adapter = IoAdapter::new(out);
+        self.write_to(&mut adapter)
+            .map_err(|_| adapter.take_error())
+    }
+}
+
+/// Writes JSON text one construct at a time as a `Visitor`, so that it can be driven by a
+/// tree as well as by a stream of tokens.
+pub(crate) struct Emitter<'o, W> {
+    out: W,
+    options: &'o GeneratorOptions,
+    stack: Vec<Frame>,
+    // The elements of the innermost array while it may still fit on one line, see
+    // `GeneratorOptions::inline_arrays`. Its `[` has not been written yet.
+    pending: Option<Pending>,
+    // The comments which end the current line, and those which go on lines of their own
+    // before whatever is written next.
+    trailing: Vec<String>,
+    leading: Vec<String>,
+}
+
+struct Frame {
+    array: bool,
+    layout: Layout,
+    items: usize,
+}
+
+struct Pending {
+    items: Vec<String>,
+    width: usize,
+}
+
+impl<'o, W: fmt::Write> Emitter<'o, W> {
+    pub(crate) fn new(out: W, options: &'o GeneratorOptions) -> Self {
+        Self {
+            out,
+            options,
+            stack: Vec::new(),
+            pending: None,
+            trailing: Vec::new(),
+            leading: Vec::new(),
+        }
+    }
+
+    /// Ends the text after the top-level value.
+    pub(crate) fn finish(&mut self) -> Result<(), Error> {
+        self.write_comments(0).map_err(write_error)?;
+        if self.options.final_newline {
+            self.out.write_str(self.newline()).map_err(write_error)?;
+        }
+        Ok(())
+    }
+
+    fn open(&mut self, array: bool) -> fmt::Result {
+        self.flush_pending()?;
+        self.before_value()?;
+        match self.options.inline_arrays {
+            Some(_) if array && self.options.layout == Layout::Pretty => {
+                self.pending = Some(Pending {
+                    items: Vec::new(),
+                    width: 2,
+                })
+            }
+            _ => self.out.write_char(if array { '[' } else { '{' })?,
+        }
+        self.stack.push(Frame {
+            array,
+            layout: self.options.layout,
+            items: 0,
+        });
+        Ok(())
+    }
+
+    fn close_array(&mut self) -> fmt::Result {
+        match self.pending.take() {
+            Some(pending) => {
+                self.stack.pop();
+                self.out.write_char('[')?;
+                for (i, item) in pending.items.iter().enumerate() {
+                    if i > 0 {
+                        self.out.write_str(self.comma())?;
+                    }
+                    self.out.write_str(item)?;
+                }
+                self.out.write_char(']')
+            }
+            None => self.end_container(']'),
+        }
+    }
+
+    fn write_key(&mut self, key: &str) -> fmt::Result {
+        self.separate()?;
+        json_util::write_quoted(&mut self.out, key, &self.options.escape)?;
+        self.out.write_str(self.colon())
+    }
+
+    fn write_string(&mut self, value: &str) -> fmt::Result {
+        if self.pending.is_some() {
+            return self.scalar(&json_util::quote(value, &self.options.escape));
+        }
+        self.before_value()?;
+        json_util::write_quoted(&mut self.out, value, &self.options.escape)
     }
 
-    fn inc_indent(&self, value: &str, indent_size: usize) -> String {
-        format!("{}{}", " ".repeat(indent_size), value)
+    fn scalar(&mut self, text: &str) -> fmt::Result {
+        let comma = self.comma();
+        if let Some(pending) = &mut self.pending {
+            if !pending.items.is_empty() {
+                pending.width += comma.chars().count();
+            }
+            pending.width += text.chars().count();
+            pending.items.push(text.to_string());
+
+            if Some(pending.width) > self.options.inline_arrays {
+                self.flush_pending()?;
+            }
+            return Ok(());
+        }
+
+        self.before_value()?;
+        self.out.write_str(text)
     }
 
-    fn add_prefix(&self, value: String, prefix: &str) -> String {
-        format!("{}{}", prefix, value)
+    // Gives up keeping the innermost array on one line and writes what it has so far.
+    fn flush_pending(&mut self) -> fmt::Result {
+        if let Some(pending) = self.pending.take() {
+            self.out.write_char('[')?;
+            for item in pending.items {
+                self.separate()?;
+                self.out.write_str(&item)?;
+            }
+        }
+        Ok(())
     }
 
-    fn generate_impl(&self, node: &Node, prefix: &str) -> String {
-        match node {
-            Node::Null => "null".to_string(),
-            Node::Number(num) => num.to_string(),
-            Node::String(value) => self.generate_string(value.to_string()),
-            Node::Boolean(b) => b.to_string(),
-            Node::Object(kvm) => self.generate_object(kvm, prefix),
-            Node::Array(arr) => self.generate_array(arr, prefix),
+    fn end_container(&mut self, close: char) -> fmt::Result {
+        let commented = !(self.leading.is_empty() && self.trailing.is_empty());
+        let frame = self.stack.pop().expect("a container to end");
+        if frame.items > 0 && frame.layout == Layout::Pretty && self.options.trailing_commas {
+            self.out.write_char(',')?;
+        }
+        self.write_comments(self.stack.len() + 1)?;
+        if (frame.items > 0 || commented) && frame.layout == Layout::Pretty {
+            self.out.write_str(self.newline())?;
+            self.write_indent(self.stack.len())?;
         }
+        self.out.write_char(close)
     }
 
-    fn generate_string(&self, value: String) -> String {
-        format!("\"{}\"", value)
+    // Separates an array element from the previous one. Object values have already been
+    // separated by their key.
+    fn before_value(&mut self) -> fmt::Result {
+        match self.stack.last() {
+            Some(frame) if frame.array => self.separate(),
+            Some(_) => Ok(()),
+            // The comments before the top-level value.
+            None => {
+                for comment in std::mem::take(&mut self.leading) {
+                    self.out.write_str(&comment)?;
+                    self.out.write_str(self.newline())?;
+                }
+                Ok(())
+            }
+        }
     }
 
-    fn generate_object_inner(&self, kvm: &IndexMap<String, Node>, prefix: &str) -> String {
-        let new_prefix = self.inc_indent(prefix, self.indent_size);
+    // Writes what goes before the next member or element of the innermost container.
+    fn separate(&mut self) -> fmt::Result {
+        let depth = self.stack.len();
+        let frame = self.stack.last_mut().expect("a container to separate");
+        let layout = frame.layout;
+        let first = frame.items == 0;
+        frame.items += 1;
+
+        if !first {
+            self.out.write_str(match layout {
+                Layout::Pretty => ",",
+                _ => self.comma(),
+            })?;
+        }
+        self.write_comments(depth)?;
+        if layout == Layout::Pretty {
+            self.out.write_str(self.newline())?;
+            self.write_indent(depth)?;
+        }
+        Ok(())
+    }
 
-        let mut inner = String::new();
-        for (key, node) in kvm {
-            let member = format!(
-                "{}: {},\n",
-                self.generate_string(key.to_string()),
-                self.generate_impl(node, &new_prefix)
-            );
-            let member = self.add_prefix(member, &new_prefix);
-            inner = format!("{}{}", inner, member);
+    // Ends the current line with the trailing comments, then writes the leading ones on lines
+    // of their own, indented by `depth`.
+    fn write_comments(&mut self, depth: usize) -> fmt::Result {
+        for comment in std::mem::take(&mut self.trailing) {
+            self.out.write_char(' ')?;
+            self.out.write_str(&comment)?;
         }
+        for comment in std::mem::take(&mut self.leading) {
+            self.out.write_str(self.newline())?;
+            self.write_indent(depth)?;
+            self.out.write_str(&comment)?;
+        }
+        Ok(())
+    }
 
-        // delete the end of comma and \n
-        inner.pop();
-        inner.pop();
+    fn newline(&self) -> &'static str {
+        self.options.line_ending.as_str()
+    }
 
-        inner
+    fn write_indent(&mut self, depth: usize) -> fmt::Result {
+        for _ in 0..depth {
+            self.out.write_str(&self.options.indent)?;
+        }
+        Ok(())
+    }
+
+    // Returns the separator written between a key and its value.
+    fn colon(&self) -> &'static str {
+        if self.options.layout == Layout::Minified {
+            return ":";
+        }
+
+        match (
+            self.options.space_before_colon,
+            self.options.space_after_colon,
+        ) {
+            (false, false) => ":",
+            (false, true) => ": ",
+            (true, false) => " :",
+            (true, true) => " : ",
+        }
     }
 
-    fn generate_object(&self, kvm: &IndexMap<String, Node>, prefix: &str) -> String {
-        format!(
-            "{{\n{}\n{}}}",
-            self.generate_object_inner(kvm, prefix),
-            prefix
-        )
+    // Returns the separator written between values on a single line.
+    fn comma(&self) -> &'static str {
+        match self.options.layout {
+            Layout::Minified => ",",
+            _ => ", ",
+        }
+    }
+}
+
+impl<'a, W: fmt::Write> Visitor<'a> for Emitter<'_, W> {
+    type Error = Error;
+
+    fn begin_object(&mut self) -> Result<(), Error> {
+        self.open(false).map_err(write_error)
     }
 
-    fn generate_array(&self, arr: &[Node], prefix: &str) -> String {
-        format!("[\n{}\n{}]", self.generate_array_inner(arr, prefix), prefix)
+    fn end_object(&mut self) -> Result<(), Error> {
+        self.end_container('}').map_err(write_error)
     }
 
-    fn generate_array_inner(&self, arr: &[Node], prefix: &str) -> String {
-        let new_prefix = self.inc_indent(prefix, self.indent_size);
+    fn key(&mut self, key: Cow<'a, str>) -> Result<(), Error> {
+        self.write_key(&key).map_err(write_error)
+    }
 
-        let mut inner = String::new();
-        for node in arr {
-            let elm = self.add_prefix(self.generate_impl(node, &new_prefix), &new_prefix);
-            inner = format!("{}{},\n", inner, elm);
+    fn begin_array(&mut self) -> Result<(), Error> {
+        self.open(true).map_err(write_error)
+    }
+
+    fn end_array(&mut self) -> Result<(), Error> {
+        self.close_array().map_err(write_error)
+    }
+
+    fn string(&mut self, value: Cow<'a, str>) -> Result<(), Error> {
+        self.write_string(&value).map_err(write_error)
+    }
+
+    fn number(&mut self, num: Cow<'a, str>) -> Result<(), Error> {
+        // JSON cannot represent the Infinity and NaN of JSON5; the reader warns about them.
+        let num = match num.as_ref() {
+            "Infinity" | "-Infinity" | "NaN" => "null",
+            num => num,
+        };
+        self.scalar(num).map_err(write_error)
+    }
+
+    fn boolean(&mut self, b: bool) -> Result<(), Error> {
+        self.scalar(if b { "true" } else { "false" })
+            .map_err(write_error)
+    }
+
+    fn null(&mut self) -> Result<(), Error> {
+        self.scalar("null").map_err(write_error)
+    }
+
+    fn comment(&mut self, text: Cow<'a, str>, trailing: bool) -> Result<(), Error> {
+        if self.options.layout != Layout::Pretty {
+            return Ok(());
         }
+        // An array with comments is not kept on one line.
+        self.flush_pending().map_err(write_error)?;
+        if trailing && self.leading.is_empty() {
+            self.trailing.push(text.into_owned());
+        } else {
+            self.leading.push(text.into_owned());
+        }
+        Ok(())
+    }
+}
+
+// `fmt::Error` carries nothing, so whoever owns the sink has to find out what went wrong,
+// see `IoAdapter`.
+fn write_error(_: fmt::Error) -> Error {
+    Error::io(io::Error::other("formatter error"), Position::default())
+}
+
+// Lets the generator write into an `io::Write`, keeping the underlying error which
+// `fmt::Error` cannot carry.
+pub(crate) struct IoAdapter<W> {
+    inner: W,
+    error: Option<io::Error>,
+}
+
+impl<W> IoAdapter<W> {
+    pub(crate) fn new(inner: W) -> Self {
+        Self { inner, error: None }
+    }
 
-        // delete the end of comma and \n
-        inner.pop();
-        inner.pop();
+    pub(crate) fn failed(&self) -> bool {
+        self.error.is_some()
+    }
+
+    // Returns the error which made the last write fail.
+    pub(crate) fn take_error(&mut self) -> io::Error {
+        self.error
+            .take()
+            .unwrap_or_else(|| io::Error::other("formatter error"))
+    }
+}
 
-        inner
+impl<W: io::Write> fmt::Write for IoAdapter<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.inner.write_all(s.as_bytes()).map_err(|err| {
+            self.error = Some(err);
+            fmt::Error
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use indexmap::IndexMap;
+
     use super::*;
+    use crate::tokenizer::{Token, Tokenizer};
+
+    fn round_trip(value: &str, options: EscapeOptions) -> String {
+        let node = Node::String(value.to_string());
+        let gen = Generator::with_options(
+            &node,
+            &GeneratorOptions {
+                escape: options,
+                ..Default::default()
+            },
+        );
+        let json = gen.generate();
+
+        let mut tokenizer = Tokenizer::new(&json);
+        assert_eq!(tokenizer.next_token(), Ok(Token::String(value.into())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::Eof));
+
+        json
+    }
 
     #[test]
     fn generate_int() {
         let node = Node::Number("123".to_string());
-        let gen = Generator::new(node, 4);
+        let gen = Generator::new(&node, 4);
 
         assert_eq!(gen.generate(), "123");
     }
@@ -104,7 +502,7 @@ mod tests {
     #[test]
     fn generate_boolean() {
         let node = Node::Boolean(true);
-        let gen = Generator::new(node, 4);
+        let gen = Generator::new(&node, 4);
 
         assert_eq!(gen.generate(), "true");
     }
@@ -112,15 +510,81 @@ mod tests {
     #[test]
     fn generate_string() {
         let node = Node::String("apple".to_string());
-        let gen = Generator::new(node, 4);
+        let gen = Generator::new(&node, 4);
 
         assert_eq!(gen.generate(), "\"apple\"");
     }
 
+    #[test]
+    fn generate_string_with_escapes() {
+        let node = Node::String("a\"b\\c/d\u{8}\u{c}\n\r\t\u{0}\u{1f}".to_string());
+        let gen = Generator::new(&node, 4);
+
+        assert_eq!(gen.generate(), r#""a\"b\\c/d\b\f\n\r\t\u0000\u001f""#);
+    }
+
+    #[test]
+    fn generate_object_key_with_escapes() {
+        let node = Node::Object(IndexMap::from([("say \"hi\"\n".to_string(), Node::Null)]));
+        let gen = Generator::new(&node, 4);
+
+        assert_eq!(gen.generate(), "{\n    \"say \\\"hi\\\"\\n\": null\n}");
+    }
+
+    #[test]
+    fn generate_string_round_trip() {
+        let options = EscapeOptions::default();
+        let value = "caf\u{e9} \u{1F600} </script> & \u{2028} \"\\/\n\u{7f}";
+
+        assert_eq!(
+            round_trip(value, options),
+            format!(
+                "\"{}\"",
+                "caf\u{e9} \u{1F600} </script> & \u{2028} \\\"\\\\/\\n\u{7f}"
+            )
+        );
+    }
+
+    #[test]
+    fn generate_string_ascii_only() {
+        let options = EscapeOptions {
+            ascii_only: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            round_trip("caf\u{e9} \u{1F600}", options),
+            r#""caf\u00e9 \ud83d\ude00""#
+        );
+    }
+
+    #[test]
+    fn generate_string_escape_slash() {
+        let options = EscapeOptions {
+            escape_slash: true,
+            ..Default::default()
+        };
+
+        assert_eq!(round_trip("a/b", options), r#""a\/b""#);
+    }
+
+    #[test]
+    fn generate_string_html_safe() {
+        let options = EscapeOptions {
+            html_safe: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            round_trip("</script> & \u{2028}\u{2029}", options),
+            r#""\u003c/script\u003e \u0026 \u2028\u2029""#
+        );
+    }
+
     #[test]
     fn generate_null() {
         let node = Node::Null;
-        let gen = Generator::new(node, 4);
+        let gen = Generator::new(&node, 4);
 
         assert_eq!(gen.generate(), "null");
     }
@@ -133,7 +597,7 @@ mod tests {
             ("elm3".to_string(), Node::String("apple".to_string())),
             ("elm4".to_string(), Node::Boolean(false)),
         ]));
-        let gen = Generator::new(node, 4);
+        let gen = Generator::new(&node, 4);
 
         #[rustfmt::skip]
         assert_eq!(
@@ -156,7 +620,7 @@ mod tests {
             Node::String("apple".to_string()),
             Node::Boolean(true),
         ]));
-        let gen = Generator::new(node, 4);
+        let gen = Generator::new(&node, 4);
 
         #[rustfmt::skip]
         assert_eq!(
@@ -171,6 +635,264 @@ mod tests {
         ));
     }
 
+    fn nested_node() -> Node {
+        Node::Object(IndexMap::from([
+            (
+                "a".to_string(),
+                Node::Array(Vec::from([
+                    Node::Number("1".to_string()),
+                    Node::Object(IndexMap::new()),
+                    Node::Array(Vec::new()),
+                ])),
+            ),
+            ("b".to_string(), Node::String("x y".to_string())),
+        ]))
+    }
+
+    #[test]
+    fn generate_minified() {
+        let node = nested_node();
+        let gen = Generator::with_options(
+            &node,
+            &GeneratorOptions {
+                layout: Layout::Minified,
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(gen.generate(), r#"{"a":[1,{},[]],"b":"x y"}"#);
+    }
+
+    #[test]
+    fn generate_single_line() {
+        let node = nested_node();
+        let gen = Generator::with_options(
+            &node,
+            &GeneratorOptions {
+                layout: Layout::SingleLine,
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(gen.generate(), r#"{"a": [1, {}, []], "b": "x y"}"#);
+    }
+
+    #[test]
+    fn generate_with_tabs_and_crlf() {
+        let node = nested_node();
+        let gen = Generator::with_options(
+            &node,
+            &GeneratorOptions {
+                indent: "\t".to_string(),
+                line_ending: LineEnding::CrLf,
+                final_newline: true,
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(
+            gen.generate(),
+            "{\r\n\t\"a\": [\r\n\t\t1,\r\n\t\t{},\r\n\t\t[]\r\n\t],\r\n\t\"b\": \"x y\"\r\n}\r\n"
+        );
+    }
+
+    #[test]
+    fn generate_colon_spacing() {
+        let node = Node::Object(IndexMap::from([("a".to_string(), Node::Null)]));
+        let options = |space_before_colon, space_after_colon, layout| GeneratorOptions {
+            space_before_colon,
+            space_after_colon,
+            layout,
+            ..Default::default()
+        };
+
+        let gen = Generator::with_options(&node, &options(true, true, Layout::Pretty));
+        assert_eq!(gen.generate(), "{\n    \"a\" : null\n}");
+
+        let gen = Generator::with_options(&node, &options(false, false, Layout::Pretty));
+        assert_eq!(gen.generate(), "{\n    \"a\":null\n}");
+
+        let gen = Generator::with_options(&node, &options(true, false, Layout::SingleLine));
+        assert_eq!(gen.generate(), r#"{"a" :null}"#);
+
+        let gen = Generator::with_options(&node, &options(true, true, Layout::Minified));
+        assert_eq!(gen.generate(), r#"{"a":null}"#);
+    }
+
+    #[test]
+    fn generate_empty_containers() {
+        let node = Node::Object(IndexMap::new());
+        assert_eq!(Generator::new(&node, 4).generate(), "{}");
+
+        let node = Node::Array(Vec::new());
+        assert_eq!(Generator::new(&node, 4).generate(), "[]");
+
+        let node = Node::Object(IndexMap::from([
+            ("a".to_string(), Node::Object(IndexMap::new())),
+            ("b".to_string(), Node::Array(Vec::new())),
+        ]));
+        assert_eq!(
+            Generator::new(&node, 4).generate(),
+            "{\n    \"a\": {},\n    \"b\": []\n}"
+        );
+    }
+
+    #[test]
+    fn generate_json5_numbers() {
+        let node = Node::Array(vec![
+            Node::Number("Infinity".into()),
+            Node::Number("-Infinity".into()),
+            Node::Number("NaN".into()),
+        ]);
+        let options = GeneratorOptions {
+            layout: Layout::Minified,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            Generator::with_options(&node, &options).generate(),
+            "[null,null,null]"
+        );
+    }
+
+    #[test]
+    fn generate_trailing_commas() {
+        let node = nested_node();
+        let options = GeneratorOptions {
+            indent: " ".repeat(2),
+            trailing_commas: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            Generator::with_options(&node, &options).generate(),
+            "{\n  \"a\": [\n    1,\n    {},\n    [],\n  ],\n  \"b\": \"x y\",\n}"
+        );
+
+        let options = GeneratorOptions {
+            layout: Layout::SingleLine,
+            ..options
+        };
+        assert_eq!(
+            Generator::with_options(&node, &options).generate(),
+            "{\"a\": [1, {}, []], \"b\": \"x y\"}"
+        );
+    }
+
+    #[test]
+    fn generate_comments() {
+        let input = "// top\n{\"a\": 1, // one\n /* b */ \"b\": [2 // two\n ], \"c\": [\n// none\n], \"d\": {} /* d */} // end\n";
+        let (node, comments, _) =
+            crate::parse_with_comments(input, Default::default(), Default::default()).unwrap();
+        let generate = |options: &GeneratorOptions| {
+            Generator::with_options(&node, options)
+                .with_comments(&comments)
+                .generate()
+        };
+
+        assert_eq!(
+            generate(&GeneratorOptions::default()),
+            "// top\n{\n    \"a\": 1, // one\n    /* b */\n    \"b\": [\n        2 // two\n    ],\n    \"c\": [\n        // none\n    ],\n    \"d\": {} /* d */\n} // end"
+        );
+        assert_eq!(
+            generate(&GeneratorOptions {
+                inline_arrays: Some(80),
+                ..Default::default()
+            }),
+            generate(&GeneratorOptions::default())
+        );
+        assert_eq!(
+            generate(&GeneratorOptions {
+                layout: Layout::Minified,
+                ..Default::default()
+            }),
+            r#"{"a":1,"b":[2],"c":[],"d":{}}"#
+        );
+    }
+
+    #[test]
+    fn generate_inline_arrays() {
+        let node = Node::Object(IndexMap::from([
+            (
+                "short".to_string(),
+                Node::Array(Vec::from([
+                    Node::Number("1".to_string()),
+                    Node::Number("2".to_string()),
+                    Node::Number("3".to_string()),
+                ])),
+            ),
+            (
+                "long".to_string(),
+                Node::Array(Vec::from([
+                    Node::String("abcdefgh".to_string()),
+                    Node::String("ijklmnop".to_string()),
+                ])),
+            ),
+            (
+                "nested".to_string(),
+                Node::Array(Vec::from([Node::Array(Vec::new())])),
+            ),
+        ]));
+        let gen = Generator::with_options(
+            &node,
+            &GeneratorOptions {
+                inline_arrays: Some(20),
+                ..Default::default()
+            },
+        );
+
+        #[rustfmt::skip]
+        assert_eq!(
+            gen.generate(),
+            format!("{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}",
+                r#"{"#,
+                r#"    "short": [1, 2, 3],"#,
+                r#"    "long": ["#,
+                r#"        "abcdefgh","#,
+                r#"        "ijklmnop""#,
+                r#"    ],"#,
+                r#"    "nested": ["#,
+                r#"        []"#,
+                r#"    ]"#,
+                r#"}"#,
+        ));
+    }
+
+    #[test]
+    fn write_to_sinks() {
+        let node = nested_node();
+        let gen = Generator::new(&node, 2);
+
+        let mut json = String::new();
+        gen.write_to(&mut json).unwrap();
+        assert_eq!(json, gen.generate());
+
+        let mut bytes = Vec::new();
+        gen.write_io(&mut bytes).unwrap();
+        assert_eq!(String::from_utf8(bytes).unwrap(), json);
+    }
+
+    #[test]
+    fn write_io_reports_errors() {
+        struct Full;
+
+        impl io::Write for Full {
+            fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+                Err(io::Error::new(io::ErrorKind::WriteZero, "disk full"))
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let node = nested_node();
+        let err = Generator::new(&node, 4).write_io(Full).unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::WriteZero);
+        assert_eq!(err.to_string(), "disk full");
+    }
+
     #[test]
     fn generate_large_json1() {
         #[rustfmt::skip]
@@ -198,7 +920,7 @@ mod tests {
                         ])
                 ))
             ]));
-        let gen = Generator::new(node, 4);
+        let gen = Generator::new(&node, 4);
 
         #[rustfmt::skip]
         assert_eq!(
@@ -1,3 +1,5 @@
+use std::fmt;
+
 pub fn is_whitespace(c: char) -> bool {
     c == '\x20'     // Space
     || c == '\x09'  // Horizontal tab
@@ -5,19 +7,56 @@ pub fn is_whitespace(c: char) -> bool {
     || c == '\x0D' // Carriage return
 }
 
-pub fn is_unescaped(c: char) -> bool {
-    ('\x20'..='\x21').contains(&c) || ('\x23'..='\x5B').contains(&c) || c >= '\x5D'
+/// Whitespace which JSON5 allows besides that of JSON.
+pub fn is_json5_whitespace(c: char) -> bool {
+    matches!(
+        c,
+        '\x0B'         // Vertical tab
+        | '\x0C'       // Form feed
+        | '\u{A0}'     // No-break space
+        | '\u{FEFF}'   // Byte order mark
+        | '\u{2028}'   // Line separator
+        | '\u{2029}'   // Paragraph separator
+        | '\u{1680}' | '\u{2000}'..='\u{200A}' | '\u{202F}' | '\u{205F}' | '\u{3000}' // Zs
+    )
+}
+
+/// Whether `c` can start an unquoted JSON5 key, which is an ECMAScript identifier.
+pub fn is_identifier_start(c: char) -> bool {
+    c.is_alphabetic() || c == '$' || c == '_'
 }
 
-// TODO: fix about uXXXX
-pub fn is_escape_target(c: char) -> bool {
-    let escape_targets = [
-        '\x22', '\x5C', '\x2F', '\x62', '\x66', '\x6E', '\x72', '\x74',
-    ];
-    escape_targets.contains(&c)
+pub fn is_identifier_part(c: char) -> bool {
+    is_identifier_start(c) || c.is_numeric() || c == '\u{200C}' || c == '\u{200D}'
+}
+
+/// Converts the digits of a hexadecimal number of any size to decimal.
+pub fn hex_to_decimal(hex: &str) -> String {
+    // The decimal digits, least significant first.
+    let mut digits = vec![0u32];
+    for h in hex.chars() {
+        let mut carry = h.to_digit(16).expect("a hexadecimal digit");
+        for digit in digits.iter_mut() {
+            let value = *digit * 16 + carry;
+            *digit = value % 10;
+            carry = value / 10;
+        }
+        while carry > 0 {
+            digits.push(carry % 10);
+            carry /= 10;
+        }
+    }
+    digits
+        .iter()
+        .rev()
+        .map(|&digit| char::from_digit(digit, 10).unwrap())
+        .collect()
+}
+
+pub fn is_unescaped(c: u8) -> bool {
+    (0x20..=0x21).contains(&c) || (0x23..=0x5B).contains(&c) || c >= 0x5D
 }
 
-// TODO: fix about uXXXX
 pub fn escape(c: char) -> Option<char> {
     match c {
         '\x22' => Some('\u{0022}'), // "    quotation mark  U+0022
@@ -28,7 +67,78 @@ pub fn escape(c: char) -> Option<char> {
         '\x6E' => Some('\u{000A}'), // n    line feed       U+000A
         '\x72' => Some('\u{000D}'), // r    carriage return U+000D
         '\x74' => Some('\u{0009}'), // t    tab             U+0009
-        // uXXXX                U+XXXX
         _ => None,
     }
 }
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct EscapeOptions {
+    /// Escape every non-ASCII character as \uXXXX, using surrogate pairs above U+FFFF.
+    pub ascii_only: bool,
+    /// Escape / as \/.
+    pub escape_slash: bool,
+    /// Escape <, >, & and U+2028/U+2029 so that the output can be embedded in HTML or JavaScript.
+    pub html_safe: bool,
+}
+
+pub fn quote(value: &str, options: &EscapeOptions) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    write_quoted(&mut quoted, value, options).expect("writing to a String cannot fail");
+    quoted
+}
+
+/// Writes `value` as a JSON string literal, copying runs of characters which need no
+/// escaping in one piece.
+pub fn write_quoted<W: fmt::Write>(
+    out: &mut W,
+    value: &str,
+    options: &EscapeOptions,
+) -> fmt::Result {
+    out.write_char('"')?;
+    let mut run = 0;
+    for (i, c) in value.char_indices() {
+        let escaped = match c {
+            '\x22' => Some("\\\""),
+            '\x5C' => Some("\\\\"),
+            '\x08' => Some("\\b"),
+            '\x0C' => Some("\\f"),
+            '\x0A' => Some("\\n"),
+            '\x0D' => Some("\\r"),
+            '\x09' => Some("\\t"),
+            '\x2F' if options.escape_slash => Some("\\/"),
+            '<' | '>' | '&' | '\u{2028}' | '\u{2029}' if options.html_safe => None,
+            c if c < '\x20' => None,
+            c if !c.is_ascii() && options.ascii_only => None,
+            _ => continue,
+        };
+
+        out.write_str(&value[run..i])?;
+        match escaped {
+            Some(escaped) => out.write_str(escaped)?,
+            None => write_unicode_escape(out, c)?,
+        }
+        run = i + c.len_utf8();
+    }
+    out.write_str(&value[run..])?;
+    out.write_char('"')
+}
+
+fn write_unicode_escape<W: fmt::Write>(out: &mut W, c: char) -> fmt::Result {
+    let mut buf = [0; 2];
+    for unit in c.encode_utf16(&mut buf) {
+        write!(out, "\\u{:04x}", unit)?;
+    }
+    Ok(())
+}
+
+pub fn is_high_surrogate(code: u32) -> bool {
+    (0xD800..=0xDBFF).contains(&code)
+}
+
+pub fn is_low_surrogate(code: u32) -> bool {
+    (0xDC00..=0xDFFF).contains(&code)
+}
+
+pub fn combine_surrogates(high: u32, low: u32) -> Option<char> {
+    char::from_u32(0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00))
+}
@@ -0,0 +1,98 @@
+use std::io;
+
+pub mod borrowed;
+pub mod comments;
+pub mod cst;
+pub mod error;
+pub mod generator;
+mod json_util;
+pub mod parser;
+pub mod reader;
+pub mod span;
+pub mod stream;
+pub mod tokenizer;
+pub mod visitor;
+
+pub use comments::{Comment, Comments};
+pub use error::{Diagnostic, Error, Severity};
+pub use generator::{Generator, GeneratorOptions, Layout, LineEnding};
+pub use json_util::EscapeOptions;
+pub use parser::{parse_first, DuplicateKeys, Node, Parser, ParserOptions};
+pub use reader::{Event, Reader, Scalar};
+pub use span::{Position, Span};
+pub use stream::StreamTokenizer;
+pub use tokenizer::{SpannedToken, Token, TokenSource, Tokenizer, TokenizerOptions};
+pub use visitor::Visitor;
+
+/// Parses `input` as a single JSON text.
+pub fn parse(input: &str) -> Result<Node, Error> {
+    parse_with_options(input, TokenizerOptions::default(), ParserOptions::default())
+}
+
+pub fn parse_with_options(
+    input: &str,
+    tokenizer_options: TokenizerOptions,
+    options: ParserOptions,
+) -> Result<Node, Error> {
+    parse_with_diagnostics(input, tokenizer_options, options).map(|(node, _)| node)
+}
+
+/// Parses `input` like `parse_with_options`, and also returns the warnings about it and,
+/// with `ParserOptions::recover`, the syntax errors which were read past.
+pub fn parse_with_diagnostics(
+    input: &str,
+    tokenizer_options: TokenizerOptions,
+    options: ParserOptions,
+) -> Result<(Node, Vec<Diagnostic>), Error> {
+    let parsed = parser::build(Tokenizer::with_options(input, tokenizer_options), options)?;
+    Ok((parsed.node.into_owned(), parsed.diagnostics))
+}
+
+/// Parses JSONC `input`, i.e. JSON text with `//` and `/* */` comments, like
+/// `parse_with_diagnostics`, and also returns the comments attached to the values of the tree.
+/// `Generator::with_comments` writes them back out.
+pub fn parse_with_comments(
+    input: &str,
+    tokenizer_options: TokenizerOptions,
+    options: ParserOptions,
+) -> Result<(Node, Comments, Vec<Diagnostic>), Error> {
+    let tokenizer_options = TokenizerOptions {
+        comments: true,
+        ..tokenizer_options
+    };
+    let parsed = parser::build(Tokenizer::with_options(input, tokenizer_options), options)?;
+    Ok((
+        parsed.node.into_owned(),
+        parsed.comments,
+        parsed.diagnostics,
+    ))
+}
+
+/// Parses `input` into a tree which borrows every key, string and number without escapes
+/// from `input`.
+pub fn parse_borrowed(input: &str) -> Result<borrowed::Node<'_>, Error> {
+    parser::build(Tokenizer::new(input), ParserOptions::default()).map(|parsed| parsed.node)
+}
+
+/// Formats `node` as indented JSON text.
+pub fn to_string_pretty(node: &Node, options: &GeneratorOptions) -> String {
+    Generator::with_options(node, options).generate()
+}
+
+/// Writes `node` as formatted JSON text into `writer`.
+pub fn to_writer<W: io::Write>(
+    writer: W,
+    node: &Node,
+    options: &GeneratorOptions,
+) -> io::Result<()> {
+    Generator::with_options(node, options).write_io(writer)
+}
+
+/// Formats `node` as JSON text without any whitespace.
+pub fn to_string(node: &Node) -> String {
+    let options = GeneratorOptions {
+        layout: Layout::Minified,
+        ..Default::default()
+    };
+    Generator::with_options(node, &options).generate()
+}
@@ -1,45 +1,592 @@
-// TODO: add option to specify indent (space or tab, count)
 // TODO: add cui usage to README.md
-mod generator;
-mod json_util;
-mod parser;
-mod tokenizer;
+mod diff;
 
-use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
 
-use clap::Parser;
-use generator::Generator;
-use tokenizer::Token;
-
-use crate::tokenizer::Tokenizer;
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser};
+use json_pretty::{
+    Comments, Diagnostic, DuplicateKeys, Error, EscapeOptions, Generator, GeneratorOptions, Layout,
+    LineEnding, Node, ParserOptions, Severity, TokenizerOptions,
+};
 
 /// Simple lint for JSON text
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
 struct Args {
-    /// JSON text which you want to lint
-    json_text: String,
+    /// JSON files which you want to lint; reads stdin when no file or - is given
+    files: Vec<PathBuf>,
+
+    #[clap(long, conflicts_with = "files")]
+    /// JSON text which you want to lint, instead of files
+    text: Option<String>,
 
     #[clap(long, short, default_value_t = 4)]
     /// indent size
     n: usize,
+
+    #[clap(long, conflicts_with = "indent")]
+    /// indent with a tab per level instead of spaces
+    tabs: bool,
+
+    #[clap(long)]
+    /// indent with the given string per level instead of spaces
+    indent: Option<String>,
+
+    #[clap(long)]
+    /// end lines with CRLF instead of LF
+    crlf: bool,
+
+    #[clap(long)]
+    /// do not end the output with a line ending
+    no_final_newline: bool,
+
+    #[clap(long)]
+    /// put a space before each :
+    space_before_colon: bool,
+
+    #[clap(long)]
+    /// do not put a space after each :
+    no_space_after_colon: bool,
+
+    #[clap(long, value_name = "WIDTH")]
+    /// keep arrays of scalars on one line when they fit in WIDTH characters
+    inline_arrays: Option<usize>,
+
+    #[clap(long)]
+    /// replace unpaired surrogates in \uXXXX escapes with U+FFFD
+    lossy: bool,
+
+    #[clap(long)]
+    /// accept // and /* */ comments and keep them in the output, which drops them unless it is
+    /// pretty-printed, so that it cannot be combined with --write and --minify or --compact
+    jsonc: bool,
+
+    #[clap(long)]
+    /// read JSON5, e.g. with unquoted keys, single quotes, trailing commas and comments, and
+    /// write it as JSON; Infinity and NaN become null
+    json5: bool,
+
+    #[clap(long)]
+    /// accept a comma after the last member or element, and warn about it
+    allow_trailing_commas: bool,
+
+    #[clap(long, value_name = "DEPTH", default_value_t = 128)]
+    /// reject objects and arrays nested more deeply than DEPTH
+    max_depth: usize,
+
+    #[clap(
+        long,
+        value_name = "POLICY",
+        default_value = "warn",
+        possible_values = &["error", "warn", "first", "last", "all"],
+        conflicts_with = "stream"
+    )]
+    /// what to do with a key which appears twice in an object: fail, or keep the last value
+    /// (warn or last), the first or both; every duplicate is reported either way
+    duplicate_keys: String,
+
+    #[clap(long)]
+    /// end the last member or element of objects and arrays spanning several lines with a
+    /// comma, which JSON does not allow
+    trailing_commas: bool,
+
+    #[clap(long)]
+    /// escape all non-ASCII characters as \uXXXX
+    ascii: bool,
+
+    #[clap(long)]
+    /// escape / as \/
+    escape_slash: bool,
+
+    #[clap(long)]
+    /// escape <, >, & and U+2028/U+2029 for embedding in HTML or JavaScript
+    html_safe: bool,
+
+    #[clap(long, conflicts_with = "minify")]
+    /// print everything on a single line with a space after each : and ,
+    compact: bool,
+
+    #[clap(long)]
+    /// print everything on a single line without any whitespace
+    minify: bool,
+
+    #[clap(long, short, conflicts_with_all = &["check", "text"])]
+    /// rewrite the files in place
+    write: bool,
+
+    #[clap(long)]
+    /// list the files which are not formatted and exit with status 3 if there are any
+    check: bool,
+
+    #[clap(long)]
+    /// print a unified diff between each input and its formatted output
+    diff: bool,
+
+    #[clap(long, conflicts_with_all = &["write", "check", "diff", "text"])]
+    /// format the input while reading it, in bounded memory; the output is cut short on an error,
+    /// and duplicate keys are written as they are
+    stream: bool,
+}
+
+struct Input {
+    name: String,
+    path: Option<PathBuf>,
+    // Read as bytes, so that the tokenizer reports invalid UTF-8 with its position.
+    bytes: Vec<u8>,
+}
+
+// A parsed tree which is dropped with `Node::drop_deep`, since --max-depth lets it be nested
+// deeply enough for the usual drop to overflow the stack.
+struct Tree(Node);
+
+impl Drop for Tree {
+    fn drop(&mut self) {
+        std::mem::replace(&mut self.0, Node::Null).drop_deep();
+    }
+}
+
+fn read_inputs(args: &Args) -> Vec<Result<Input, (String, io::Error)>> {
+    if let Some(text) = &args.text {
+        return vec![Ok(Input {
+            name: "<text>".to_string(),
+            path: None,
+            bytes: text.clone().into_bytes(),
+        })];
+    }
+
+    let stdin = PathBuf::from("-");
+    let files = if args.files.is_empty() {
+        std::slice::from_ref(&stdin)
+    } else {
+        &args.files[..]
+    };
+
+    files
+        .iter()
+        .map(|path| {
+            let result = if path == &stdin {
+                let mut bytes = Vec::new();
+                io::stdin().read_to_end(&mut bytes).map(|_| bytes)
+            } else {
+                fs::read(path)
+            };
+
+            let (name, path) = if path == &stdin {
+                ("<stdin>".to_string(), None)
+            } else {
+                (path.display().to_string(), Some(path.clone()))
+            };
+            match result {
+                Ok(bytes) => Ok(Input { name, path, bytes }),
+                Err(err) => Err((name, err)),
+            }
+        })
+        .collect()
 }
 
-fn pretty_json(json: String, indent_size: usize) -> Result<String, String> {
-    let tokenizer = Tokenizer::new(json);
-    let tokens = tokenizer.collect::<Result<VecDeque<Token>, _>>()?;
+// Replaces the file through a temporary file in the same directory, so that readers never
+// see a partially written file, and keeps the permissions of the original.
+fn write_atomic(path: &Path, contents: &str) -> io::Result<()> {
+    let path = fs::canonicalize(path)?;
+    let permissions = fs::metadata(&path)?.permissions();
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temp = path.with_file_name(format!(".{}.{}.tmp", file_name, process::id()));
+
+    let result = (|| {
+        let mut file = File::create(&temp)?;
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()?;
+        fs::set_permissions(&temp, permissions)?;
+        fs::rename(&temp, &path)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+// Formats each input into stdout as it is read, without keeping the inputs in memory.
+fn stream(
+    args: &Args,
+    options: TokenizerOptions,
+    parser_options: ParserOptions,
+    generator_options: &GeneratorOptions,
+) -> i32 {
+    let stdin = PathBuf::from("-");
+    let files = if args.files.is_empty() {
+        std::slice::from_ref(&stdin)
+    } else {
+        &args.files[..]
+    };
+
+    let mut status = 0;
+    for path in files {
+        let (name, reader): (String, io::Result<Box<dyn Read>>) = if path == &stdin {
+            ("<stdin>".to_string(), Ok(Box::new(io::stdin())))
+        } else {
+            (
+                path.display().to_string(),
+                File::open(path).map(|file| Box::new(file) as Box<dyn Read>),
+            )
+        };
+        let reader = match reader {
+            Ok(reader) => reader,
+            Err(err) => {
+                eprintln!("error: {}: {}", name, err);
+                if status == 0 {
+                    status = 1;
+                }
+                continue;
+            }
+        };
+
+        if files.len() > 1 {
+            println!("==> {} <==", name);
+        }
+        let stdout = io::stdout();
+        let mut writer = io::BufWriter::new(stdout.lock());
+        let result = json_pretty::stream::format(
+            reader,
+            &mut writer,
+            options,
+            parser_options,
+            generator_options,
+        );
+        let flushed = writer.flush();
+        drop(writer);
+
+        match result {
+            Ok(()) => {}
+            Err(Error::Io { message, .. }) => {
+                eprintln!("error: {}: {}", name, message);
+                if status == 0 {
+                    status = 1;
+                }
+            }
+            Err(err) => {
+                // The output so far has been written; end its line before the error.
+                println!();
+                eprintln!("{}", report(&name, None, &err));
+                if status == 0 {
+                    status = exit_code(&err);
+                }
+            }
+        }
+        if let Err(err) = flushed {
+            eprintln!("error: <stdout>: {}", err);
+            return 1;
+        }
+    }
+    status
+}
 
-    let mut parser = parser::Parser::new(tokens);
-    let node = parser.parse()?;
+fn report(name: &str, source: Option<&str>, err: &Error) -> String {
+    render("error", name, source, err)
+}
 
-    let gen = Generator::new(node, indent_size);
-    Ok(gen.generate())
+fn report_diagnostic(name: &str, source: &str, diagnostic: &Diagnostic) -> String {
+    let label = match diagnostic.severity {
+        Severity::Warning => "warning",
+        Severity::Error => "error",
+    };
+    render(label, name, Some(source), &diagnostic.error)
+}
+
+// How many characters of the offending line are shown on either side of an error.
+const CONTEXT: usize = 60;
+
+// Renders the error with the offending source line and a caret under it, like rustc does.
+// Without the source, only the location is shown.
+fn render(label: &str, name: &str, source: Option<&str>, err: &Error) -> String {
+    let start = err.span().start;
+    let end = err.span().end;
+    let source = match source {
+        Some(source) => source,
+        None => {
+            return format!(
+                "{}: {}\n --> {}:{}:{}",
+                label,
+                err.message(),
+                name,
+                start.line,
+                start.column
+            )
+        }
+    };
+    // The line is found from the offset of the error rather than by counting lines, and only
+    // up to `CONTEXT` characters on either side of the error are echoed.
+    let mut offset = start.offset.min(source.len());
+    while !source.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    let mut from = offset;
+    let mut cut_before = false;
+    for (count, (i, c)) in source[..offset].char_indices().rev().enumerate() {
+        if c == '\n' {
+            break;
+        }
+        if count == CONTEXT {
+            cut_before = true;
+            break;
+        }
+        from = i;
+    }
+    let mut to = offset;
+    let mut cut_after = false;
+    for (count, (i, c)) in source[offset..].char_indices().enumerate() {
+        if c == '\n' {
+            break;
+        }
+        if count == CONTEXT {
+            cut_after = true;
+            break;
+        }
+        to = offset + i + c.len_utf8();
+    }
+    let before = &source[from..offset];
+    let after = source[offset..to].trim_end_matches('\r');
+
+    let mut indent = before
+        .chars()
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect::<String>();
+    let mut line = format!("{}{}", before, after);
+    if cut_before {
+        indent.insert_str(0, "   ");
+        line.insert_str(0, "...");
+    }
+    if cut_after {
+        line.push_str("...");
+    }
+    let rest = after.chars().count();
+    let width = if end.line == start.line {
+        end.column.saturating_sub(start.column).min(rest)
+    } else {
+        rest
+    };
+
+    let number = start.line.to_string();
+    let gutter = " ".repeat(number.len());
+    format!(
+        "{}: {}\n{gutter}--> {}:{}:{}\n{gutter} |\n{number} | {line}\n{gutter} | {indent}{}",
+        label,
+        err.message(),
+        name,
+        start.line,
+        start.column,
+        "^".repeat(width.max(1)),
+    )
+}
+
+// The error which the tokenizer finds in text which is not valid UTF-8: where the invalid
+// bytes are, or a syntax error before them.
+fn invalid_utf8(bytes: &[u8], options: TokenizerOptions) -> Error {
+    let tokenizer = json_pretty::Tokenizer::from_slice_with_options(bytes, options);
+    tokenizer
+        .filter_map(Result::err)
+        .next()
+        .unwrap_or_else(|| Error::InvalidUtf8 {
+            span: Default::default(),
+        })
+}
+
+// Each kind of error has its own exit status so that scripts can react to it.
+fn exit_code(err: &Error) -> i32 {
+    match err {
+        Error::UnexpectedChar { .. } => 10,
+        Error::UnexpectedToken { .. } => 11,
+        Error::UnterminatedString { .. } => 12,
+        Error::InvalidEscape { .. } => 13,
+        Error::InvalidNumber { .. } => 14,
+        Error::TrailingContent { .. } => 15,
+        Error::DepthLimitExceeded { .. } => 16,
+        Error::UnexpectedEof { .. } => 17,
+        Error::InvalidUtf8 { .. } => 18,
+        Error::DuplicateKey { .. } => 19,
+        Error::TrailingComma { .. } => 20,
+        Error::NonFiniteNumber { .. } => 21,
+        Error::Io { .. } => 1,
+    }
 }
 
 fn main() {
     let args = Args::parse();
-    match pretty_json(args.json_text, args.n) {
-        Ok(json) => println!("{}", json),
-        Err(err) => eprintln!("Error: {}", err),
+    // Only the pretty layout writes comments, so rewriting the files in any other would lose
+    // them.
+    if args.jsonc && args.write && (args.minify || args.compact) {
+        Args::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--jsonc --write would drop the comments from the files with --minify or --compact",
+            )
+            .exit();
+    }
+    let options = TokenizerOptions {
+        lossy_surrogates: args.lossy,
+        comments: args.jsonc,
+        json5: args.json5,
+    };
+    let parser_options = ParserOptions {
+        max_depth: args.max_depth,
+        duplicate_keys: match args.duplicate_keys.as_str() {
+            "error" => DuplicateKeys::Error,
+            "warn" => DuplicateKeys::Warn,
+            "first" => DuplicateKeys::KeepFirst,
+            "last" => DuplicateKeys::KeepLast,
+            _ => DuplicateKeys::KeepAll,
+        },
+        recover: false,
+        json5: args.json5,
+        trailing_commas: args.allow_trailing_commas,
+    };
+    let layout = if args.minify {
+        Layout::Minified
+    } else if args.compact {
+        Layout::SingleLine
+    } else {
+        Layout::Pretty
+    };
+    let indent = if args.tabs {
+        "\t".to_string()
+    } else {
+        args.indent.clone().unwrap_or_else(|| " ".repeat(args.n))
+    };
+    let line_ending = if args.crlf {
+        LineEnding::CrLf
+    } else {
+        LineEnding::Lf
+    };
+    let generator_options = GeneratorOptions {
+        indent,
+        layout,
+        line_ending,
+        final_newline: !args.no_final_newline,
+        space_before_colon: args.space_before_colon,
+        space_after_colon: !args.no_space_after_colon,
+        inline_arrays: args.inline_arrays,
+        trailing_commas: args.trailing_commas,
+        escape: EscapeOptions {
+            ascii_only: args.ascii,
+            escape_slash: args.escape_slash,
+            html_safe: args.html_safe,
+        },
+    };
+
+    if args.stream {
+        process::exit(stream(&args, options, parser_options, &generator_options));
+    }
+
+    let inputs = read_inputs(&args);
+    let labeled = inputs.len() > 1;
+    let mut status = 0;
+    for input in inputs {
+        let input = match input {
+            Ok(input) => input,
+            Err((name, err)) => {
+                eprintln!("error: {}: {}", name, err);
+                if status == 0 {
+                    status = 1;
+                }
+                continue;
+            }
+        };
+
+        let source = match String::from_utf8(input.bytes) {
+            Ok(source) => source,
+            Err(err) => {
+                let bytes = err.into_bytes();
+                let err = invalid_utf8(&bytes, options);
+                let source = String::from_utf8_lossy(&bytes);
+                eprintln!("{}", report(&input.name, Some(&source), &err));
+                if status == 0 {
+                    status = exit_code(&err);
+                }
+                continue;
+            }
+        };
+
+        // Reads on after syntax errors, so that all of them are reported at once.
+        let parser_options = ParserOptions {
+            recover: true,
+            ..parser_options
+        };
+        let result = if args.jsonc {
+            json_pretty::parse_with_comments(&source, options, parser_options)
+        } else {
+            json_pretty::parse_with_diagnostics(&source, options, parser_options)
+                .map(|(node, diagnostics)| (node, Comments::default(), diagnostics))
+        };
+        let (tree, comments) = match result {
+            Ok((node, comments, diagnostics)) => {
+                let tree = Tree(node);
+                for diagnostic in &diagnostics {
+                    eprintln!("{}", report_diagnostic(&input.name, &source, diagnostic));
+                }
+                let first_error = diagnostics
+                    .iter()
+                    .find(|diagnostic| diagnostic.severity == Severity::Error);
+                if let Some(diagnostic) = first_error {
+                    if status == 0 {
+                        status = exit_code(&diagnostic.error);
+                    }
+                    continue;
+                }
+                (tree, comments)
+            }
+            Err(err) => {
+                eprintln!("{}", report(&input.name, Some(&source), &err));
+                if status == 0 {
+                    status = exit_code(&err);
+                }
+                continue;
+            }
+        };
+
+        let generator =
+            Generator::with_options(&tree.0, &generator_options).with_comments(&comments);
+        if !(args.write || args.check || args.diff) {
+            if labeled {
+                println!("==> {} <==", input.name);
+            }
+            let stdout = io::stdout();
+            let mut writer = io::BufWriter::new(stdout.lock());
+            let result = generator.write_io(&mut writer).and_then(|_| writer.flush());
+            if let Err(err) = result {
+                eprintln!("error: <stdout>: {}", err);
+                process::exit(1);
+            }
+            continue;
+        }
+
+        let formatted = generator.generate();
+        if formatted == source {
+            continue;
+        }
+        if args.diff {
+            print!("{}", diff::unified_diff(&input.name, &source, &formatted));
+        }
+        if args.check {
+            println!("{}", input.name);
+            if status == 0 {
+                status = 3;
+            }
+        }
+        if args.write {
+            let result = match &input.path {
+                Some(path) => write_atomic(path, &formatted),
+                None => Err(io::Error::other("--write needs a file, not stdin")),
+            };
+            if let Err(err) = result {
+                eprintln!("error: {}: {}", input.name, err);
+                if status == 0 {
+                    status = 1;
+                }
+            }
+        }
     }
+    process::exit(status);
 }
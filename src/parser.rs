@@ -1,166 +1,591 @@
-use std::collections::VecDeque;
+use std::borrow::Cow;
+use std::collections::{HashMap, VecDeque};
 
 use indexmap::IndexMap;
 
-use crate::tokenizer::Token;
+use crate::borrowed;
+use crate::comments::{self, Comment, Comments};
+use crate::error::{Diagnostic, Error};
+use crate::reader::Reader;
+use crate::span::Span;
+use crate::tokenizer::{SpannedToken, Token, TokenSource, Tokenizer};
+use crate::visitor::Visitor;
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum Node {
     Null,
     Object(IndexMap<String, Node>),
+    /// An object in which some key appears more than once, with all of its members in
+    /// order. Only built under `DuplicateKeys::KeepAll`.
+    MultiObject(Vec<(String, Node)>),
     Array(Vec<Node>),
     Boolean(bool),
     Number(String),
     String(String),
 }
 
-pub struct Parser {
-    tokens: VecDeque<Token>,
-}
+impl Node {
+    pub fn is_null(&self) -> bool {
+        matches!(self, Node::Null)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Node::Boolean(b) => Some(*b),
+            _ => None,
+        }
+    }
 
-impl Parser {
-    pub fn new(tokens: VecDeque<Token>) -> Self {
-        Self { tokens }
+    /// Returns the number as it is written in the source text.
+    pub fn as_number(&self) -> Option<&str> {
+        match self {
+            Node::Number(num) => Some(num),
+            _ => None,
+        }
     }
 
-    pub fn parse(&mut self) -> Result<Node, String> {
-        self.json_text()
+    pub fn as_f64(&self) -> Option<f64> {
+        self.as_number()?.parse().ok()
     }
 
-    fn json_text(&mut self) -> Result<Node, String> {
-        self.value()
+    pub fn as_i64(&self) -> Option<i64> {
+        self.as_number()?.parse().ok()
     }
 
-    fn value(&mut self) -> Result<Node, String> {
-        match self.front() {
-            Some(Token::LeftCurlyBranckt) => self.object(),
-            Some(Token::LeftSquareBrancket) => self.array(),
-            Some(Token::Number(_)) => self.int(),
-            Some(Token::String(_)) => self.string(),
-            Some(Token::Boolean(_)) => self.boolean(),
-            Some(Token::Null) => self.null(),
-            Some(token) => Err(format!(
-                "Parse found an unexpected token {:#?} while parsing value.",
-                token
-            )),
-            None => Err("Parse found an unexpected token while parsing value.".to_string()),
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Node::String(value) => Some(value),
+            _ => None,
         }
     }
 
-    fn consume(&mut self, token: Token) -> Result<(), String> {
-        match self.pop() {
-            Some(head) if head == token => Ok(()),
-            Some(head) => Err(format!(
-                "Expected a token {:#?}, but found an unexpected token {:#?}",
-                token, head
-            )),
-            None => Err(format!("Expected a token {:#?}", token)),
+    pub fn as_array(&self) -> Option<&[Node]> {
+        match self {
+            Node::Array(values) => Some(values),
+            _ => None,
         }
     }
 
-    fn assume(&mut self, token: Token) -> bool {
-        match self.front() {
-            Some(head) if head == &token => {
-                self.pop();
-                true
-            }
-            _ => false,
+    pub fn as_object(&self) -> Option<&IndexMap<String, Node>> {
+        match self {
+            Node::Object(kvm) => Some(kvm),
+            _ => None,
         }
     }
 
-    fn front(&self) -> Option<&Token> {
-        self.tokens.front()
+    pub fn as_multi_object(&self) -> Option<&[(String, Node)]> {
+        match self {
+            Node::MultiObject(members) => Some(members),
+            _ => None,
+        }
+    }
+
+    /// Returns the member named `key` if this is an object, or the first one of them.
+    pub fn get(&self, key: &str) -> Option<&Node> {
+        match self {
+            Node::MultiObject(members) => members
+                .iter()
+                .find_map(|(name, value)| (name == key).then_some(value)),
+            _ => self.as_object()?.get(key),
+        }
     }
 
-    fn pop(&mut self) -> Option<Token> {
-        self.tokens.pop_front()
+    /// Returns the `index`-th element if this is an array.
+    pub fn get_index(&self, index: usize) -> Option<&Node> {
+        self.as_array()?.get(index)
     }
 
-    fn object(&mut self) -> Result<Node, String> {
-        let mut kvm = IndexMap::new();
-        self.consume(Token::LeftCurlyBranckt)?;
+    /// Drops the tree without recursing, however deeply it is nested. The usual drop recurses
+    /// once for each level of nesting, so a tree nested thousands of levels deep, which only
+    /// parses with a `ParserOptions::max_depth` far above the default, must be dropped with
+    /// this instead.
+    pub fn drop_deep(mut self) {
+        let mut stack = Vec::new();
+        take_children(&mut self, &mut stack);
+        while let Some(mut node) = stack.pop() {
+            take_children(&mut node, &mut stack);
+        }
+    }
+}
+
+fn take_children(node: &mut Node, stack: &mut Vec<Node>) {
+    match node {
+        Node::Object(kvm) => stack.extend(kvm.drain(..).map(|(_, value)| value)),
+        Node::MultiObject(members) => stack.extend(members.drain(..).map(|(_, value)| value)),
+        Node::Array(values) => stack.append(values),
+        _ => {}
+    }
+}
+
+/// What the parser does when a key appears more than once in the same object. Every
+/// duplicate is reported with the positions of both occurrences, as an `Error::DuplicateKey`
+/// warning unless the policy is `Error`; the other policies only decide which value is kept.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicateKeys {
+    /// Reject the input with `Error::DuplicateKey`. With `ParserOptions::recover`, report it as
+    /// an error instead and keep the last value, as `Warn` does.
+    Error,
+    /// Keep the last value, in the place of the first occurrence.
+    #[default]
+    Warn,
+    KeepFirst,
+    /// The same as `Warn`.
+    KeepLast,
+    /// Keep every member, turning the object into a `Node::MultiObject`.
+    KeepAll,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ParserOptions {
+    /// How deeply objects and arrays may be nested before the input is rejected with
+    /// `Error::DepthLimitExceeded`. This also bounds the recursion when a tree is dropped: a
+    /// tree parsed with a limit in the thousands or above has to be dropped with
+    /// `Node::drop_deep`, or dropping it may overflow the stack.
+    pub max_depth: usize,
+    /// What to do with a key which appears twice in an object. By default, each duplicate is
+    /// reported as a warning and the last value is kept.
+    pub duplicate_keys: DuplicateKeys,
+    /// Instead of stopping at the first syntax error, note it as a diagnostic and read on,
+    /// so that all of them are found in one run. The tree then holds whatever could be read,
+    /// with null standing in for missing or invalid values.
+    pub recover: bool,
+    /// Read JSON5 as tokenized with `TokenizerOptions::json5`, which adds unquoted keys and
+    /// trailing commas to JSON.
+    pub json5: bool,
+    /// Accept a comma after the last member of an object or the last element of an array,
+    /// and report each one as a warning.
+    pub trailing_commas: bool,
+}
 
-        if self.assume(Token::RightCurlyBranckt) {
-            return Ok(Node::Object(kvm));
+impl Default for ParserOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: 128,
+            duplicate_keys: DuplicateKeys::default(),
+            recover: false,
+            json5: false,
+            trailing_commas: false,
         }
+    }
+}
 
-        let (key, value) = self.member()?;
-        kvm.insert(key, value);
+pub struct Parser<'a> {
+    tokens: VecDeque<SpannedToken<'a>>,
+    last_span: Span,
+    options: ParserOptions,
+    diagnostics: Vec<Diagnostic>,
+    comments: Comments,
+}
 
-        loop {
-            if self.assume(Token::RightCurlyBranckt) {
+/// Parses the first JSON value from `tokenizer` and leaves the tokenizer right after it,
+/// so that the rest of the input can be obtained by `Tokenizer::rest` or parsed again.
+pub fn parse_first(tokenizer: &mut Tokenizer) -> Result<Node, Error> {
+    let mut tokens = VecDeque::new();
+    let mut depth = 0usize;
+
+    loop {
+        let spanned = tokenizer.next_spanned_token()?;
+        match spanned.token {
+            Token::LeftCurlyBranckt | Token::LeftSquareBrancket => depth += 1,
+            Token::RightCurlyBranckt | Token::RightSquareBrancket => {
+                depth = depth.saturating_sub(1)
+            }
+            Token::Eof => {
+                tokens.push_back(spanned);
                 break;
             }
+            // Comments before the value do not start it.
+            Token::Comment(_) if depth == 0 => {
+                tokens.push_back(spanned);
+                continue;
+            }
+            _ => {}
+        }
+        tokens.push_back(spanned);
 
-            self.consume(Token::Comma)?;
-            let (key, value) = self.member()?;
-            kvm.insert(key, value);
+        if depth == 0 {
+            break;
         }
+    }
+
+    Parser::new(tokens).parse()
+}
 
-        Ok(Node::Object(kvm))
+impl<'a> TokenSource<'a> for Parser<'a> {
+    // Once the tokens run out, the input is taken to end right after the last one.
+    fn next_spanned_token(&mut self) -> Result<SpannedToken<'a>, Error> {
+        let spanned = self.tokens.pop_front().unwrap_or(SpannedToken {
+            token: Token::Eof,
+            span: Span::new(self.last_span.end, self.last_span.end),
+        });
+        self.last_span = spanned.span;
+        Ok(spanned)
     }
+}
 
-    fn member(&mut self) -> Result<(String, Node), String> {
-        let key = match self.string()? {
-            Node::String(value) => value,
-            _ => unreachable!(),
-        };
+impl<'a> Parser<'a> {
+    pub fn new<T: Into<SpannedToken<'a>>>(tokens: impl IntoIterator<Item = T>) -> Self {
+        Self::with_options(tokens, ParserOptions::default())
+    }
+
+    pub fn with_options<T: Into<SpannedToken<'a>>>(
+        tokens: impl IntoIterator<Item = T>,
+        options: ParserOptions,
+    ) -> Self {
+        Self {
+            tokens: tokens.into_iter().map(Into::into).collect(),
+            last_span: Span::default(),
+            options,
+            diagnostics: Vec::new(),
+            comments: Comments::default(),
+        }
+    }
+
+    pub fn parse(&mut self) -> Result<Node, Error> {
+        self.parse_borrowed().map(borrowed::Node::into_owned)
+    }
 
-        self.consume(Token::Colon)?;
+    /// Parses the tokens into a tree which borrows from the tokens' source text.
+    pub fn parse_borrowed(&mut self) -> Result<borrowed::Node<'a>, Error> {
+        let options = self.options;
+        let parsed = build(&mut *self, options)?;
+        self.diagnostics = parsed.diagnostics;
+        self.comments = parsed.comments;
+        Ok(parsed.node)
+    }
 
-        let value = self.value()?;
+    /// The problems with the input found by the last parse, in the order of their positions.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
 
-        Ok((key, value))
+    /// The comments among the tokens of the last parse, attached to the values of the tree.
+    pub fn comments(&self) -> &Comments {
+        &self.comments
     }
+}
 
-    fn array(&mut self) -> Result<Node, String> {
-        let mut values = Vec::new();
-        self.consume(Token::LeftSquareBrancket)?;
+/// A JSON text read by `build`.
+pub(crate) struct Parsed<'a> {
+    pub(crate) node: borrowed::Node<'a>,
+    pub(crate) diagnostics: Vec<Diagnostic>,
+    pub(crate) comments: Comments,
+}
 
-        if self.assume(Token::RightSquareBrancket) {
-            return Ok(Node::Array(values));
+/// Reads a whole JSON text from `tokens` into a tree, together with the problems found in it
+/// and its comments.
+pub(crate) fn build<'a, S: TokenSource<'a>>(
+    tokens: S,
+    options: ParserOptions,
+) -> Result<Parsed<'a>, Error> {
+    let mut builder = TreeBuilder::new(options);
+    let mut reader = Reader::from_tokens_with_options(tokens, options);
+    reader.visit(&mut builder)?;
+
+    let mut diagnostics = reader.diagnostics().to_vec();
+    diagnostics.append(&mut builder.diagnostics);
+    diagnostics.sort_by_key(|diagnostic| diagnostic.error.span().start.offset);
+    // The comments after the top-level value.
+    let pending = std::mem::take(&mut builder.pending);
+    if !pending.is_empty() {
+        builder.comments.entry("").after.extend(pending);
+    }
+    Ok(Parsed {
+        // Only a recovering parse can end without having read any value.
+        node: builder.root.take().unwrap_or(borrowed::Node::Null),
+        diagnostics,
+        comments: std::mem::take(&mut builder.comments),
+    })
+}
+
+// Builds a tree from the constructs handed to it, keeping the containers being built on a
+// stack instead of recursing.
+struct TreeBuilder<'a> {
+    duplicate_keys: DuplicateKeys,
+    recover: bool,
+    stack: Vec<Open<'a>>,
+    // The key of the next member with its span.
+    key: Option<(Cow<'a, str>, Span)>,
+    // The span of the construct being handed over.
+    span: Span,
+    root: Option<borrowed::Node<'a>>,
+    diagnostics: Vec<Diagnostic>,
+    // Whether the last construct handed over ended a value, and the key of that value.
+    after_value: bool,
+    previous_key: Option<Cow<'a, str>>,
+    // The comments which go before the next value, or at the end of the innermost container.
+    pending: Vec<Comment>,
+    comments: Comments,
+}
+
+// Which value of the innermost container a pointer leads to.
+#[derive(Clone, Copy)]
+enum Child {
+    Previous,
+    Next,
+}
+
+struct Open<'a> {
+    node: borrowed::Node<'a>,
+    // The key under which the container goes into its parent.
+    key: Option<(Cow<'a, str>, Span)>,
+    // The spans of the keys of an object, in the order of its members.
+    key_spans: Vec<Span>,
+    // The span of the first occurrence of each key, once the object keeps every member.
+    first_spans: HashMap<Cow<'a, str>, Span>,
+}
+
+impl<'a> TreeBuilder<'a> {
+    fn new(options: ParserOptions) -> Self {
+        Self {
+            duplicate_keys: options.duplicate_keys,
+            recover: options.recover,
+            stack: Vec::new(),
+            key: None,
+            span: Span::default(),
+            root: None,
+            diagnostics: Vec::new(),
+            after_value: false,
+            previous_key: None,
+            pending: Vec::new(),
+            comments: Comments::default(),
         }
+    }
 
-        values.push(self.value()?);
+    fn open(&mut self, node: borrowed::Node<'a>) -> Result<(), Error> {
+        self.attach_pending(Child::Next);
+        self.after_value = false;
+        self.stack.push(Open {
+            node,
+            key: self.key.take(),
+            key_spans: Vec::new(),
+            first_spans: HashMap::new(),
+        });
+        Ok(())
+    }
 
-        loop {
-            if self.assume(Token::RightSquareBrancket) {
-                break;
+    fn close(&mut self) -> Result<(), Error> {
+        if !self.pending.is_empty() {
+            let pointer = self.pointer(None);
+            let pending = std::mem::take(&mut self.pending);
+            self.comments.entry(&pointer).end.extend(pending);
+        }
+        let open = self.stack.pop().expect("an open container");
+        self.key = open.key;
+        self.add(open.node)
+    }
+
+    // Adds a scalar to the innermost container.
+    fn add_scalar(&mut self, node: borrowed::Node<'a>) -> Result<(), Error> {
+        self.attach_pending(Child::Next);
+        self.add(node)
+    }
+
+    fn add(&mut self, node: borrowed::Node<'a>) -> Result<(), Error> {
+        self.after_value = true;
+        self.previous_key = self.key.as_ref().map(|(key, _)| key.clone());
+        let open = match self.stack.last_mut() {
+            Some(open) => open,
+            None => {
+                self.root = Some(node);
+                return Ok(());
             }
-            self.consume(Token::Comma)?;
+        };
 
-            values.push(self.value()?);
+        let kvm = match &mut open.node {
+            borrowed::Node::Object(kvm) => kvm,
+            borrowed::Node::Array(values) => {
+                values.push(node);
+                return Ok(());
+            }
+            borrowed::Node::MultiObject(members) => {
+                let (key, span) = self.key.take().expect("a key");
+                match open.first_spans.get(&key) {
+                    Some(&first) => {
+                        self.diagnostics
+                            .push(Diagnostic::warning(Error::DuplicateKey {
+                                key: key.to_string(),
+                                first,
+                                span,
+                            }))
+                    }
+                    None => {
+                        open.first_spans.insert(key.clone(), span);
+                    }
+                }
+                members.push((key, node));
+                return Ok(());
+            }
+            _ => unreachable!("only containers are open"),
+        };
+
+        let (key, span) = self.key.take().expect("a key");
+        let index = match kvm.get_index_of(&key) {
+            Some(index) => index,
+            None => {
+                kvm.insert(key, node);
+                open.key_spans.push(span);
+                return Ok(());
+            }
+        };
+
+        let duplicate = Error::DuplicateKey {
+            key: key.to_string(),
+            first: open.key_spans[index],
+            span,
+        };
+        let diagnostic = match self.duplicate_keys {
+            DuplicateKeys::Error if !self.recover => return Err(duplicate),
+            DuplicateKeys::Error => Diagnostic::error(duplicate),
+            _ => Diagnostic::warning(duplicate),
+        };
+        self.diagnostics.push(diagnostic);
+        match self.duplicate_keys {
+            DuplicateKeys::KeepFirst => {}
+            DuplicateKeys::KeepAll => {
+                open.first_spans = kvm
+                    .keys()
+                    .cloned()
+                    .zip(open.key_spans.iter().copied())
+                    .collect();
+                let mut members = std::mem::take(kvm).into_iter().collect::<Vec<_>>();
+                members.push((key, node));
+                open.node = borrowed::Node::MultiObject(members);
+            }
+            DuplicateKeys::Error | DuplicateKeys::Warn | DuplicateKeys::KeepLast => {
+                kvm[index] = node
+            }
         }
+        Ok(())
+    }
 
-        Ok(Node::Array(values))
+    fn attach_pending(&mut self, child: Child) {
+        if !self.pending.is_empty() {
+            let pointer = self.pointer(Some(child));
+            let pending = std::mem::take(&mut self.pending);
+            self.comments.entry(&pointer).before.extend(pending);
+        }
     }
 
-    fn int(&mut self) -> Result<Node, String> {
-        match self.pop() {
-            Some(Token::Number(num)) => Ok(Node::Number(num)),
-            _ => Err("Parse found an unexpected token while parsing int.".to_string()),
+    // Returns the JSON Pointer of `child` of the innermost container, or of the container
+    // itself.
+    fn pointer(&self, child: Option<Child>) -> String {
+        let mut pointer = String::new();
+        for (i, open) in self.stack.iter().enumerate() {
+            let (key, previous) = match self.stack.get(i + 1) {
+                Some(inner) => (inner.key.as_ref().map(|(key, _)| key), false),
+                None => match child {
+                    Some(Child::Previous) => (self.previous_key.as_ref(), true),
+                    Some(Child::Next) => (self.key.as_ref().map(|(key, _)| key), false),
+                    None => break,
+                },
+            };
+            let key = key.map_or("", |key| key);
+            match &open.node {
+                borrowed::Node::Array(values) => {
+                    let index = values.len() - usize::from(previous);
+                    comments::push_segment(&mut pointer, &index.to_string());
+                }
+                node => {
+                    let occurrence = self.occurrence(node, key, previous);
+                    comments::push_key(&mut pointer, key, occurrence);
+                }
+            }
         }
+        pointer
     }
 
-    fn boolean(&mut self) -> Result<Node, String> {
-        match self.pop() {
-            Some(Token::Boolean(v)) => Ok(Node::Boolean(v)),
-            _ => Err("Parse found an unexpected token while parsing boolean.".to_string()),
+    // Counts the members of `object` before the one with the key `key`, which is its last
+    // member if `added`, that have the same key. Only `KeepAll` keeps more than one of them.
+    fn occurrence(&self, object: &borrowed::Node, key: &str, added: bool) -> usize {
+        if self.duplicate_keys != DuplicateKeys::KeepAll {
+            return 0;
         }
+        let count = match object {
+            borrowed::Node::Object(kvm) => usize::from(kvm.contains_key(key)),
+            borrowed::Node::MultiObject(members) => {
+                members.iter().filter(|(other, _)| other == key).count()
+            }
+            _ => 0,
+        };
+        count - usize::from(added)
     }
+}
 
-    fn null(&mut self) -> Result<Node, String> {
-        match self.pop() {
-            Some(Token::Null) => Ok(Node::Null),
-            _ => Err("Parse found an unexpected token while parsing null.".to_string()),
+// Whatever was built before an error is dropped with `drop_deep`, however deep it is.
+impl Drop for TreeBuilder<'_> {
+    fn drop(&mut self) {
+        if let Some(root) = self.root.take() {
+            root.drop_deep();
+        }
+        for open in self.stack.drain(..) {
+            open.node.drop_deep();
         }
     }
+}
+
+impl<'a> Visitor<'a> for TreeBuilder<'a> {
+    type Error = Error;
 
-    fn string(&mut self) -> Result<Node, String> {
-        match self.pop() {
-            Some(Token::String(value)) => Ok(Node::String(value)),
-            _ => Err("Parse found an unexpected token while parsing string.".to_string()),
+    fn span(&mut self, span: Span) {
+        self.span = span;
+    }
+
+    fn begin_object(&mut self) -> Result<(), Error> {
+        self.open(borrowed::Node::Object(IndexMap::new()))
+    }
+
+    fn end_object(&mut self) -> Result<(), Error> {
+        self.close()
+    }
+
+    fn key(&mut self, key: Cow<'a, str>) -> Result<(), Error> {
+        self.key = Some((key, self.span));
+        self.attach_pending(Child::Next);
+        self.after_value = false;
+        Ok(())
+    }
+
+    fn begin_array(&mut self) -> Result<(), Error> {
+        self.open(borrowed::Node::Array(Vec::new()))
+    }
+
+    fn end_array(&mut self) -> Result<(), Error> {
+        self.close()
+    }
+
+    fn string(&mut self, value: Cow<'a, str>) -> Result<(), Error> {
+        self.add_scalar(borrowed::Node::String(value))
+    }
+
+    fn number(&mut self, num: Cow<'a, str>) -> Result<(), Error> {
+        self.add_scalar(borrowed::Node::Number(num))
+    }
+
+    fn boolean(&mut self, b: bool) -> Result<(), Error> {
+        self.add_scalar(borrowed::Node::Boolean(b))
+    }
+
+    fn null(&mut self) -> Result<(), Error> {
+        self.add_scalar(borrowed::Node::Null)
+    }
+
+    fn comment(&mut self, text: Cow<'a, str>, trailing: bool) -> Result<(), Error> {
+        let comment = Comment {
+            text: text.into_owned(),
+            trailing,
+        };
+        if trailing && self.after_value {
+            let pointer = self.pointer(Some(Child::Previous));
+            self.comments.entry(&pointer).after.push(comment);
+        } else {
+            // A comment between a key and its value goes before the key, on a line of its own.
+            self.pending.push(Comment {
+                trailing: trailing && self.key.is_none(),
+                ..comment
+            });
         }
+        Ok(())
     }
 }
 
@@ -171,14 +596,22 @@ mod tests {
     use indexmap::IndexMap;
 
     use crate::{
-        parser::{Node, Parser},
-        tokenizer::Token,
+        comments::{Attached, Comment},
+        error::{Diagnostic, Error},
+        parser::{parse_first, DuplicateKeys, Node, Parser, ParserOptions},
+        span::{Position, Span},
+        tokenizer::{SpannedToken, Token, Tokenizer, TokenizerOptions},
     };
 
+    fn parse_text(input: &str) -> Result<Node, Error> {
+        let tokens = Tokenizer::new(input).collect::<Result<VecDeque<SpannedToken>, _>>()?;
+        Parser::new(tokens).parse()
+    }
+
     #[test]
     fn parse_int() {
         let mut tokens = VecDeque::new();
-        tokens.push_back(Token::Number("123".to_string()));
+        tokens.push_back(Token::Number("123".into()));
         tokens.push_back(Token::Eof);
 
         let expected = Node::Number("123".to_string());
@@ -216,22 +649,22 @@ mod tests {
         let mut tokens = VecDeque::new();
         tokens.push_back(Token::LeftCurlyBranckt);
 
-        tokens.push_back(Token::String("elm1".to_string()));
+        tokens.push_back(Token::String("elm1".into()));
         tokens.push_back(Token::Colon);
-        tokens.push_back(Token::Number("123".to_string()));
+        tokens.push_back(Token::Number("123".into()));
         tokens.push_back(Token::Comma);
 
-        tokens.push_back(Token::String("elm2".to_string()));
+        tokens.push_back(Token::String("elm2".into()));
         tokens.push_back(Token::Colon);
-        tokens.push_back(Token::Number("456".to_string()));
+        tokens.push_back(Token::Number("456".into()));
         tokens.push_back(Token::Comma);
 
-        tokens.push_back(Token::String("elm3".to_string()));
+        tokens.push_back(Token::String("elm3".into()));
         tokens.push_back(Token::Colon);
-        tokens.push_back(Token::String("apple".to_string()));
+        tokens.push_back(Token::String("apple".into()));
         tokens.push_back(Token::Comma);
 
-        tokens.push_back(Token::String("elm4".to_string()));
+        tokens.push_back(Token::String("elm4".into()));
         tokens.push_back(Token::Colon);
         tokens.push_back(Token::Boolean(false));
 
@@ -257,13 +690,13 @@ mod tests {
 
         tokens.push_back(Token::LeftSquareBrancket);
 
-        tokens.push_back(Token::Number("123".to_string()));
+        tokens.push_back(Token::Number("123".into()));
         tokens.push_back(Token::Comma);
 
-        tokens.push_back(Token::Number("456".to_string()));
+        tokens.push_back(Token::Number("456".into()));
         tokens.push_back(Token::Comma);
 
-        tokens.push_back(Token::String("apple".to_string()));
+        tokens.push_back(Token::String("apple".into()));
         tokens.push_back(Token::Comma);
 
         tokens.push_back(Token::Boolean(true));
@@ -284,72 +717,383 @@ mod tests {
         assert_eq!(node, Ok(expected));
     }
 
+    #[test]
+    fn parse_trailing_tokens() {
+        let mut tokens = VecDeque::new();
+        tokens.push_back(Token::LeftSquareBrancket);
+        tokens.push_back(Token::Number("1".into()));
+        tokens.push_back(Token::RightSquareBrancket);
+        tokens.push_back(Token::LeftSquareBrancket);
+        tokens.push_back(Token::Number("2".into()));
+        tokens.push_back(Token::RightSquareBrancket);
+        tokens.push_back(Token::Eof);
+
+        let node = Parser::new(tokens).parse();
+
+        assert!(node.is_err());
+    }
+
+    #[test]
+    fn parse_error_position() {
+        let err = parse_text("{\"a\": 1,\n \"b\" 2}").unwrap_err();
+        assert_eq!(
+            err.span(),
+            Span::new(
+                Position {
+                    line: 2,
+                    column: 6,
+                    offset: 14
+                },
+                Position {
+                    line: 2,
+                    column: 7,
+                    offset: 15
+                }
+            )
+        );
+
+        let err = parse_text("[1] [2]").unwrap_err();
+        assert_eq!(
+            err.span().start,
+            Position {
+                line: 1,
+                column: 5,
+                offset: 4
+            }
+        );
+    }
+
+    #[test]
+    fn parse_error_position_at_eof() {
+        let err = parse_text("[1,\n").unwrap_err();
+        assert_eq!(
+            err.span().start,
+            Position {
+                line: 1,
+                column: 4,
+                offset: 3
+            }
+        );
+    }
+
+    #[test]
+    fn parse_error_kinds() {
+        assert_eq!(
+            parse_text("[1 2]").map_err(|err| err.message()),
+            Err("Expected ',', but found number 2.".to_string())
+        );
+        assert!(matches!(
+            parse_text("{1: 2}"),
+            Err(Error::UnexpectedToken { .. })
+        ));
+        assert!(matches!(
+            parse_text("[1] 2"),
+            Err(Error::TrailingContent { .. })
+        ));
+        assert!(matches!(
+            parse_text("[1,"),
+            Err(Error::UnexpectedEof { .. })
+        ));
+        assert!(matches!(parse_text(""), Err(Error::UnexpectedEof { .. })));
+    }
+
+    #[test]
+    fn parse_deep_nesting() {
+        let deep = |depth| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+
+        assert!(parse_text(&deep(128)).is_ok());
+        assert!(matches!(
+            parse_text(&deep(129)),
+            Err(Error::DepthLimitExceeded { limit: 128, .. })
+        ));
+        // Far deeper input is rejected instead of overflowing the stack.
+        assert!(matches!(
+            parse_text(&"[".repeat(100_000)),
+            Err(Error::DepthLimitExceeded { .. })
+        ));
+
+        let input = deep(200);
+        let tokens = Tokenizer::new(&input)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let options = ParserOptions {
+            max_depth: 200,
+            ..Default::default()
+        };
+        assert!(Parser::with_options(tokens, options).parse().is_ok());
+    }
+
+    #[test]
+    fn parse_comments() {
+        let input = "// top\n{\n  \"a\": 1, // one\n  /* b */\n  \"b\": [\n    2\n    // end of b\n  ], \"c\": /* c */ 3\n}\n// bottom\n";
+        let options = TokenizerOptions {
+            comments: true,
+            ..Default::default()
+        };
+        let tokens = Tokenizer::with_options(input, options)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let mut parser = Parser::new(tokens);
+        parser.parse().unwrap();
+
+        let comment = |text: &str, trailing| Comment {
+            text: text.to_string(),
+            trailing,
+        };
+        let comments = parser.comments();
+        assert_eq!(
+            comments.get(""),
+            Some(&Attached {
+                before: vec![comment("// top", false)],
+                after: vec![comment("// bottom", false)],
+                end: vec![],
+            })
+        );
+        assert_eq!(comments.get("/a").unwrap().after, [comment("// one", true)]);
+        assert_eq!(
+            comments.get("/b").unwrap().before,
+            [comment("/* b */", false)]
+        );
+        assert_eq!(
+            comments.get("/b").unwrap().end,
+            [comment("// end of b", false)]
+        );
+        assert_eq!(
+            comments.get("/c").unwrap().before,
+            [comment("/* c */", false)]
+        );
+        assert_eq!(comments.get("/b/0"), None);
+    }
+
+    #[test]
+    fn parse_duplicate_keys() {
+        let input = "{\"a\": 1, \"b\": [],\n \"a\": 2}";
+        let tokens = || {
+            Tokenizer::new(input)
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap()
+        };
+        let parse = |duplicate_keys| {
+            let options = ParserOptions {
+                duplicate_keys,
+                ..Default::default()
+            };
+            let mut parser = Parser::with_options(tokens(), options);
+            (parser.parse(), parser.diagnostics().to_vec())
+        };
+        let recover = |duplicate_keys| {
+            let options = ParserOptions {
+                duplicate_keys,
+                recover: true,
+                ..Default::default()
+            };
+            let mut parser = Parser::with_options(tokens(), options);
+            (parser.parse(), parser.diagnostics().to_vec())
+        };
+        let object = |first: &str| {
+            Node::Object(IndexMap::from([
+                ("a".to_string(), Node::Number(first.to_string())),
+                ("b".to_string(), Node::Array(Vec::new())),
+            ]))
+        };
+        let duplicate = Error::DuplicateKey {
+            key: "a".to_string(),
+            first: Span::new(
+                Position {
+                    line: 1,
+                    column: 2,
+                    offset: 1,
+                },
+                Position {
+                    line: 1,
+                    column: 5,
+                    offset: 4,
+                },
+            ),
+            span: Span::new(
+                Position {
+                    line: 2,
+                    column: 2,
+                    offset: 19,
+                },
+                Position {
+                    line: 2,
+                    column: 5,
+                    offset: 22,
+                },
+            ),
+        };
+
+        assert_eq!(
+            parse(DuplicateKeys::Error),
+            (Err(duplicate.clone()), vec![])
+        );
+        assert_eq!(
+            recover(DuplicateKeys::Error),
+            (Ok(object("2")), vec![Diagnostic::error(duplicate.clone())])
+        );
+        let warning = vec![Diagnostic::warning(duplicate)];
+        assert_eq!(
+            parse(DuplicateKeys::Warn),
+            (Ok(object("2")), warning.clone())
+        );
+        assert_eq!(
+            parse(DuplicateKeys::KeepFirst),
+            (Ok(object("1")), warning.clone())
+        );
+        assert_eq!(
+            parse(DuplicateKeys::KeepLast),
+            (Ok(object("2")), warning.clone())
+        );
+        assert_eq!(parse(DuplicateKeys::default()).1, warning);
+
+        let (node, diagnostics) = parse(DuplicateKeys::KeepAll);
+        assert_eq!(diagnostics, warning);
+        let node = node.unwrap();
+        assert_eq!(
+            node.as_multi_object().unwrap(),
+            [
+                ("a".to_string(), Node::Number("1".to_string())),
+                ("b".to_string(), Node::Array(Vec::new())),
+                ("a".to_string(), Node::Number("2".to_string())),
+            ]
+        );
+        assert_eq!(node.get("a").and_then(Node::as_i64), Some(1));
+        assert_eq!(crate::to_string(&node), "{\"a\":1,\"b\":[],\"a\":2}");
+    }
+
+    #[test]
+    fn report_every_duplicate_key_kept() {
+        let input = r#"{"a": 1, "b": 2, "a": 3, "b": 4, "a": 5}"#;
+        let tokens = Tokenizer::new(input)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let options = ParserOptions {
+            duplicate_keys: DuplicateKeys::KeepAll,
+            ..Default::default()
+        };
+        let mut parser = Parser::with_options(tokens, options);
+        let node = parser.parse().unwrap();
+        assert_eq!(node.as_multi_object().map(<[_]>::len), Some(5));
+
+        let found = parser
+            .diagnostics()
+            .iter()
+            .map(|diagnostic| match &diagnostic.error {
+                Error::DuplicateKey { key, first, span } => {
+                    (key.as_str(), first.start.offset, span.start.offset)
+                }
+                err => panic!("unexpected {:?}", err),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(found, [("a", 1, 17), ("b", 9, 25), ("a", 1, 33)]);
+    }
+
+    #[test]
+    fn parse_first_value() {
+        let mut tokenizer = Tokenizer::new(r#"{"a": [1]} [2] "rest"#);
+
+        let expected = Node::Object(IndexMap::from([(
+            "a".to_string(),
+            Node::Array(Vec::from([Node::Number("1".to_string())])),
+        )]));
+        assert_eq!(parse_first(&mut tokenizer), Ok(expected));
+        assert_eq!(tokenizer.rest(), r#" [2] "rest"#);
+
+        let expected = Node::Array(Vec::from([Node::Number("2".to_string())]));
+        assert_eq!(parse_first(&mut tokenizer), Ok(expected));
+        assert_eq!(tokenizer.rest(), r#" "rest"#);
+    }
+
+    #[test]
+    fn parse_first_scalar() {
+        let mut tokenizer = Tokenizer::new("123 xyz");
+
+        assert_eq!(
+            parse_first(&mut tokenizer),
+            Ok(Node::Number("123".to_string()))
+        );
+        assert_eq!(tokenizer.rest(), " xyz");
+    }
+
+    #[test]
+    fn parse_first_invalid() {
+        for input in ["", "]", "[1}", "[1", ":"] {
+            let mut tokenizer = Tokenizer::new(input);
+            assert!(
+                parse_first(&mut tokenizer).is_err(),
+                "{} must be rejected",
+                input
+            );
+        }
+    }
+
     #[test]
     fn parse_large_json1() {
         let mut tokens = VecDeque::new();
 
         tokens.push_back(Token::LeftCurlyBranckt);
-        tokens.push_back(Token::String("Image".to_string()));
+        tokens.push_back(Token::String("Image".into()));
         tokens.push_back(Token::Colon);
         tokens.push_back(Token::LeftCurlyBranckt);
 
-        tokens.push_back(Token::String("Width".to_string()));
+        tokens.push_back(Token::String("Width".into()));
         tokens.push_back(Token::Colon);
-        tokens.push_back(Token::Number("800".to_string()));
+        tokens.push_back(Token::Number("800".into()));
         tokens.push_back(Token::Comma);
 
-        tokens.push_back(Token::String("Height".to_string()));
+        tokens.push_back(Token::String("Height".into()));
         tokens.push_back(Token::Colon);
-        tokens.push_back(Token::Number("600".to_string()));
+        tokens.push_back(Token::Number("600".into()));
         tokens.push_back(Token::Comma);
 
-        tokens.push_back(Token::String("Title".to_string()));
+        tokens.push_back(Token::String("Title".into()));
         tokens.push_back(Token::Colon);
-        tokens.push_back(Token::String("View from 15th Floor".to_string()));
+        tokens.push_back(Token::String("View from 15th Floor".into()));
         tokens.push_back(Token::Comma);
 
-        tokens.push_back(Token::String("Thumbnail".to_string()));
+        tokens.push_back(Token::String("Thumbnail".into()));
         tokens.push_back(Token::Colon);
         tokens.push_back(Token::LeftCurlyBranckt);
 
-        tokens.push_back(Token::String("Url".to_string()));
+        tokens.push_back(Token::String("Url".into()));
         tokens.push_back(Token::Colon);
         tokens.push_back(Token::String(
-            "http://www.example.com/image/481989943".to_string(),
+            "http://www.example.com/image/481989943".into(),
         ));
         tokens.push_back(Token::Comma);
 
-        tokens.push_back(Token::String("Height".to_string()));
+        tokens.push_back(Token::String("Height".into()));
         tokens.push_back(Token::Colon);
-        tokens.push_back(Token::Number("125".to_string()));
+        tokens.push_back(Token::Number("125".into()));
         tokens.push_back(Token::Comma);
 
-        tokens.push_back(Token::String("Width".to_string()));
+        tokens.push_back(Token::String("Width".into()));
         tokens.push_back(Token::Colon);
-        tokens.push_back(Token::Number("100".to_string()));
+        tokens.push_back(Token::Number("100".into()));
 
         tokens.push_back(Token::RightCurlyBranckt);
         tokens.push_back(Token::Comma);
 
-        tokens.push_back(Token::String("Animated".to_string()));
+        tokens.push_back(Token::String("Animated".into()));
         tokens.push_back(Token::Colon);
         tokens.push_back(Token::Boolean(false));
         tokens.push_back(Token::Comma);
 
-        tokens.push_back(Token::String("IDs".to_string()));
+        tokens.push_back(Token::String("IDs".into()));
         tokens.push_back(Token::Colon);
         tokens.push_back(Token::LeftSquareBrancket);
 
-        tokens.push_back(Token::Number("116".to_string()));
+        tokens.push_back(Token::Number("116".into()));
         tokens.push_back(Token::Comma);
 
-        tokens.push_back(Token::Number("943".to_string()));
+        tokens.push_back(Token::Number("943".into()));
         tokens.push_back(Token::Comma);
 
-        tokens.push_back(Token::Number("234".to_string()));
+        tokens.push_back(Token::Number("234".into()));
         tokens.push_back(Token::Comma);
 
-        tokens.push_back(Token::Number("38793".to_string()));
+        tokens.push_back(Token::Number("38793".into()));
 
         tokens.push_back(Token::RightSquareBrancket);
 
@@ -0,0 +1,898 @@
+//! A pull parser which hands out the structure of a JSON text one event at a time.
+
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::io::Read;
+use std::marker::PhantomData;
+
+use crate::error::{Diagnostic, Error};
+use crate::parser::ParserOptions;
+use crate::span::Span;
+use crate::stream::StreamTokenizer;
+use crate::tokenizer::{SpannedToken, Token, TokenSource, Tokenizer};
+use crate::visitor::Visitor;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event<'a> {
+    StartObject,
+    EndObject,
+    StartArray,
+    EndArray,
+    /// The name of the member whose value comes next.
+    Key(Cow<'a, str>),
+    Scalar(Scalar<'a>),
+    /// A comment of JSONC text, see `TokenizerOptions::comments`. `trailing` is whether it
+    /// follows other tokens on the same line.
+    Comment {
+        text: Cow<'a, str>,
+        trailing: bool,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Scalar<'a> {
+    Null,
+    Boolean(bool),
+    /// The number as it is written in the source text.
+    Number(Cow<'a, str>),
+    String(Cow<'a, str>),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Container {
+    Object,
+    Array,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum State {
+    Value,
+    FirstElement,
+    FirstMember,
+    Member,
+    Colon,
+    AfterValue,
+    // While recovering, skipping tokens up to the next ',' or closing bracket at this level.
+    Resync,
+    // While recovering, skipping the rest of a container which is nested too deeply.
+    SkipContainer,
+    Done,
+}
+
+// A token which does not fit where it was found.
+struct Misplaced<'a> {
+    token: Token<'a>,
+    span: Span,
+    error: Error,
+}
+
+/// Checks the structure of the token stream as it goes and turns it into events, keeping
+/// nothing but the stack of open containers.
+pub struct Reader<'a, S> {
+    tokens: S,
+    stack: Vec<Container>,
+    state: State,
+    // An event which has been read ahead by `peek`.
+    peeked: Option<(Option<Event<'a>>, Span)>,
+    // Events inserted while recovering, which are handed out before reading on.
+    queue: VecDeque<(Event<'a>, Span)>,
+    // The nesting depth of the tokens being skipped while recovering.
+    skip_depth: usize,
+    diagnostics: Vec<Diagnostic>,
+    // The last comma after a member or element.
+    comma: Span,
+    // The line on which the last token other than a comment ended.
+    line: Option<usize>,
+    // The number of containers opened by the events handed out so far.
+    depth: usize,
+    span: Span,
+    options: ParserOptions,
+    marker: PhantomData<&'a str>,
+}
+
+impl<'a> Reader<'a, Tokenizer<'a>> {
+    pub fn new(input: &'a str) -> Self {
+        Self::from_tokens(Tokenizer::new(input))
+    }
+}
+
+impl<R: Read> Reader<'static, StreamTokenizer<R>> {
+    pub fn from_reader(reader: R) -> Self {
+        Self::from_tokens(StreamTokenizer::new(reader))
+    }
+}
+
+impl<'a, S: TokenSource<'a>> Iterator for Reader<'a, S> {
+    type Item = Result<Event<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_event().transpose()
+    }
+}
+
+impl<'a, S: TokenSource<'a>> Reader<'a, S> {
+    pub fn from_tokens(tokens: S) -> Self {
+        Self::from_tokens_with_options(tokens, ParserOptions::default())
+    }
+
+    pub fn from_tokens_with_options(tokens: S, options: ParserOptions) -> Self {
+        Self {
+            tokens,
+            stack: Vec::new(),
+            state: State::Value,
+            peeked: None,
+            queue: VecDeque::new(),
+            skip_depth: 0,
+            diagnostics: Vec::new(),
+            line: None,
+            comma: Span::default(),
+            depth: 0,
+            span: Span::default(),
+            options,
+            marker: PhantomData,
+        }
+    }
+
+    /// Returns the next event, or `None` once the top-level value has been read and only
+    /// whitespace follows it.
+    pub fn next_event(&mut self) -> Result<Option<Event<'a>>, Error> {
+        let (event, span) = match self.peeked.take() {
+            Some(peeked) => peeked,
+            None => self.read_event()?,
+        };
+
+        match event {
+            Some(Event::StartObject | Event::StartArray) => self.depth += 1,
+            Some(Event::EndObject | Event::EndArray) => self.depth -= 1,
+            _ => {}
+        }
+        self.span = span;
+        Ok(event)
+    }
+
+    /// Returns the next event without consuming it.
+    pub fn peek(&mut self) -> Result<Option<&Event<'a>>, Error> {
+        if self.peeked.is_none() {
+            self.peeked = Some(self.read_event()?);
+        }
+        Ok(self.peeked.as_ref().and_then(|(event, _)| event.as_ref()))
+    }
+
+    /// Skips the next value together with everything inside it. Where a key is expected, the
+    /// whole member is skipped. At the end of a container or of the input nothing is skipped.
+    pub fn skip_value(&mut self) -> Result<(), Error> {
+        let depth = self.depth;
+        loop {
+            if let None | Some(Event::EndObject | Event::EndArray) = self.peek()? {
+                if self.depth == depth {
+                    return Ok(());
+                }
+            }
+
+            let event = self.next_event()?;
+            if self.depth == depth && !matches!(event, Some(Event::Key(_) | Event::Comment { .. }))
+            {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Hands the events up to the end of the input to `visitor`.
+    pub fn visit<V: Visitor<'a>>(&mut self, visitor: &mut V) -> Result<(), V::Error> {
+        while let Some(event) = self.next_event()? {
+            visitor.span(self.span);
+            match event {
+                Event::StartObject => visitor.begin_object(),
+                Event::EndObject => visitor.end_object(),
+                Event::StartArray => visitor.begin_array(),
+                Event::EndArray => visitor.end_array(),
+                Event::Key(key) => visitor.key(key),
+                Event::Scalar(Scalar::Null) => visitor.null(),
+                Event::Scalar(Scalar::Boolean(b)) => visitor.boolean(b),
+                Event::Scalar(Scalar::Number(num)) => visitor.number(num),
+                Event::Scalar(Scalar::String(value)) => visitor.string(value),
+                Event::Comment { text, trailing } => visitor.comment(text, trailing),
+            }?;
+        }
+        Ok(())
+    }
+
+    /// The span of the token behind the last event.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// The number of containers which have been started but not ended yet.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// The syntax errors which have been recovered from so far, see `ParserOptions::recover`.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    fn read_event(&mut self) -> Result<(Option<Event<'a>>, Span), Error> {
+        loop {
+            if let Some((event, span)) = self.queue.pop_front() {
+                return Ok((Some(event), span));
+            }
+            if self.state == State::Done {
+                return Ok((None, self.span));
+            }
+
+            let SpannedToken { token, span } = match self.tokens.next_spanned_token() {
+                Ok(spanned) => spanned,
+                // Whatever follows the top-level value is trailing content, token or not.
+                Err(err) if self.state == State::AfterValue && self.stack.is_empty() => {
+                    let err = trailing(err);
+                    if !self.options.recover {
+                        return Err(err);
+                    }
+                    self.diagnostics.push(Diagnostic::error(err));
+                    self.state = State::Done;
+                    continue;
+                }
+                Err(err) if self.options.recover => {
+                    self.skip_invalid(err)?;
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+            if let Token::Comment(text) = token {
+                let trailing = self.line == Some(span.start.line);
+                return Ok((Some(Event::Comment { text, trailing }), span));
+            }
+            self.line = Some(span.end.line);
+
+            match self.step(token, span) {
+                Ok(Some(event)) => return Ok((Some(event), span)),
+                Ok(None) if self.state == State::Done => return Ok((None, span)),
+                Ok(None) => {}
+                Err(misplaced) if self.options.recover => self.recover(*misplaced),
+                Err(misplaced) => return Err(misplaced.error),
+            }
+        }
+    }
+
+    // Whether the token closes the innermost container right after a comma, and trailing
+    // commas are allowed.
+    fn closes_after_comma(&self, token: &Token<'a>) -> bool {
+        if !(self.options.json5 || self.options.trailing_commas) {
+            return false;
+        }
+        match (self.state, token) {
+            (State::Value, Token::RightSquareBrancket) => {
+                self.stack.last() == Some(&Container::Array)
+            }
+            (State::Member, Token::RightCurlyBranckt) => true,
+            _ => false,
+        }
+    }
+
+    fn step(
+        &mut self,
+        token: Token<'a>,
+        span: Span,
+    ) -> Result<Option<Event<'a>>, Box<Misplaced<'a>>> {
+        match self.state {
+            State::Value | State::Member if self.closes_after_comma(&token) => {
+                // JSON5 allows trailing commas; otherwise they are only tolerated.
+                if !self.options.json5 {
+                    let error = Error::TrailingComma { span: self.comma };
+                    self.diagnostics.push(Diagnostic::warning(error));
+                }
+                Ok(Some(self.end()))
+            }
+            State::Value => self.value(token, span).map(Some),
+            State::FirstElement => match token {
+                Token::RightSquareBrancket => Ok(Some(self.end())),
+                token => self.value(token, span).map(Some),
+            },
+            State::FirstMember => match token {
+                Token::RightCurlyBranckt => Ok(Some(self.end())),
+                token => self.key(token, span).map(Some),
+            },
+            State::Member => self.key(token, span).map(Some),
+            State::Colon => match token {
+                Token::Colon => {
+                    self.state = State::Value;
+                    Ok(None)
+                }
+                token => Err(misplaced(token, span, "':'")),
+            },
+            State::AfterValue => match (self.stack.last(), token) {
+                (None, Token::Eof) => {
+                    self.state = State::Done;
+                    Ok(None)
+                }
+                (None, token) => Err(Box::new(Misplaced {
+                    error: Error::TrailingContent {
+                        found: token.to_string(),
+                        span,
+                    },
+                    token,
+                    span,
+                })),
+                (Some(Container::Object), Token::Comma) => {
+                    self.comma = span;
+                    self.state = State::Member;
+                    Ok(None)
+                }
+                (Some(Container::Array), Token::Comma) => {
+                    self.comma = span;
+                    self.state = State::Value;
+                    Ok(None)
+                }
+                (Some(Container::Object), Token::RightCurlyBranckt)
+                | (Some(Container::Array), Token::RightSquareBrancket) => Ok(Some(self.end())),
+                (Some(_), token) => Err(misplaced(token, span, "','")),
+            },
+            State::Resync => match token {
+                Token::LeftCurlyBranckt | Token::LeftSquareBrancket => {
+                    self.skip_depth += 1;
+                    Ok(None)
+                }
+                Token::RightCurlyBranckt | Token::RightSquareBrancket | Token::Comma
+                    if self.skip_depth > 0 =>
+                {
+                    if token != Token::Comma {
+                        self.skip_depth -= 1;
+                    }
+                    Ok(None)
+                }
+                Token::RightCurlyBranckt
+                | Token::RightSquareBrancket
+                | Token::Comma
+                | Token::Eof => {
+                    self.state = State::AfterValue;
+                    self.step(token, span)
+                }
+                _ => Ok(None),
+            },
+            State::SkipContainer => match token {
+                Token::LeftCurlyBranckt | Token::LeftSquareBrancket => {
+                    self.skip_depth += 1;
+                    Ok(None)
+                }
+                Token::RightCurlyBranckt | Token::RightSquareBrancket => {
+                    self.skip_depth -= 1;
+                    if self.skip_depth == 0 {
+                        self.state = State::AfterValue;
+                    }
+                    Ok(None)
+                }
+                Token::Eof => {
+                    self.state = State::AfterValue;
+                    self.step(token, span)
+                }
+                _ => Ok(None),
+            },
+            State::Done => unreachable!(),
+        }
+    }
+
+    fn value(&mut self, token: Token<'a>, span: Span) -> Result<Event<'a>, Box<Misplaced<'a>>> {
+        let container = match token {
+            Token::LeftCurlyBranckt => Container::Object,
+            Token::LeftSquareBrancket => Container::Array,
+            Token::Null => return Ok(self.scalar(Scalar::Null)),
+            Token::Boolean(b) => return Ok(self.scalar(Scalar::Boolean(b))),
+            Token::Number(num) => {
+                if matches!(num.as_ref(), "Infinity" | "-Infinity" | "NaN") {
+                    let error = Error::NonFiniteNumber {
+                        found: num.to_string(),
+                        span,
+                    };
+                    self.diagnostics.push(Diagnostic::warning(error));
+                }
+                return Ok(self.scalar(Scalar::Number(num)));
+            }
+            Token::String(value) => return Ok(self.scalar(Scalar::String(value))),
+            token => return Err(misplaced(token, span, "a value")),
+        };
+
+        if self.stack.len() == self.options.max_depth {
+            return Err(Box::new(Misplaced {
+                token: match container {
+                    Container::Object => Token::LeftCurlyBranckt,
+                    Container::Array => Token::LeftSquareBrancket,
+                },
+                span,
+                error: Error::DepthLimitExceeded {
+                    limit: self.options.max_depth,
+                    span,
+                },
+            }));
+        }
+
+        self.stack.push(container);
+        match container {
+            Container::Object => {
+                self.state = State::FirstMember;
+                Ok(Event::StartObject)
+            }
+            Container::Array => {
+                self.state = State::FirstElement;
+                Ok(Event::StartArray)
+            }
+        }
+    }
+
+    fn scalar(&mut self, scalar: Scalar<'a>) -> Event<'a> {
+        self.state = State::AfterValue;
+        Event::Scalar(scalar)
+    }
+
+    fn key(&mut self, token: Token<'a>, span: Span) -> Result<Event<'a>, Box<Misplaced<'a>>> {
+        let json5 = self.options.json5;
+        let key = match token {
+            Token::String(key) => key,
+            Token::Identifier(key) if json5 => key,
+            // Reserved words are identifier names as well, which JSON5 allows as keys.
+            Token::Null if json5 => "null".into(),
+            Token::Boolean(b) if json5 => if b { "true" } else { "false" }.into(),
+            // Unless it is written with a sign, e.g. as `+Infinity`.
+            Token::Number(num)
+                if json5
+                    && matches!(num.as_ref(), "Infinity" | "NaN")
+                    && span.end.offset - span.start.offset == num.len() =>
+            {
+                num
+            }
+            token => return Err(misplaced(token, span, "a string")),
+        };
+        self.state = State::Colon;
+        Ok(Event::Key(key))
+    }
+
+    // Ends the innermost container.
+    fn end(&mut self) -> Event<'a> {
+        self.state = State::AfterValue;
+        match self.stack.pop() {
+            Some(Container::Object) => Event::EndObject,
+            _ => Event::EndArray,
+        }
+    }
+
+    // Notes a token which could not be read and skips it. Where a value is expected, null
+    // stands in for it; where a key is expected, the whole member is skipped.
+    fn skip_invalid(&mut self, err: Error) -> Result<(), Error> {
+        self.tokens.recover(err.clone())?;
+        let span = err.span();
+        self.diagnostics.push(Diagnostic::error(err));
+
+        match self.state {
+            State::Value | State::FirstElement => {
+                let event = self.scalar(Scalar::Null);
+                self.queue.push_back((event, span));
+            }
+            State::FirstMember | State::Member => self.resync(),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    // Notes the problem and gets back into a state from which reading can go on.
+    fn recover(&mut self, misplaced: Misplaced<'a>) {
+        let Misplaced { token, span, error } = misplaced;
+        let too_deep = matches!(error, Error::DepthLimitExceeded { .. });
+        self.diagnostics.push(Diagnostic::error(error));
+
+        if too_deep {
+            // Null stands in for the whole container.
+            let event = self.scalar(Scalar::Null);
+            self.queue.push_back((event, span));
+            self.state = State::SkipContainer;
+            self.skip_depth = 1;
+            return;
+        }
+        self.resume(token, span);
+    }
+
+    // Goes on from `token`, which has been reported already, inserting the missing tokens
+    // where it is clear which ones are missing and skipping the rest.
+    fn resume(&mut self, token: Token<'a>, span: Span) {
+        let in_object = self.stack.last() == Some(&Container::Object);
+        match (self.state, token) {
+            (_, Token::Eof) => {
+                self.insert_pending_value(span);
+                while !self.stack.is_empty() {
+                    let event = self.end();
+                    self.queue.push_back((event, span));
+                }
+                self.state = State::Done;
+            }
+            // Anything after the top-level value is left alone.
+            (State::AfterValue, _) if self.stack.is_empty() => self.state = State::Done,
+            (State::Value | State::FirstElement, _) if self.stack.is_empty() => {}
+            (_, Token::RightCurlyBranckt | Token::RightSquareBrancket) => self.close(span),
+            (State::Colon, token) => {
+                // The ':' is missing; so is the value unless one follows.
+                if starts_value(&token) {
+                    self.state = State::Value;
+                } else {
+                    self.insert_pending_value(span);
+                    self.state = State::AfterValue;
+                }
+                self.retry(token, span);
+            }
+            (State::Value, Token::Comma) if in_object => {
+                self.insert_pending_value(span);
+                self.state = State::AfterValue;
+                self.retry(Token::Comma, span);
+            }
+            (
+                State::Value | State::FirstElement | State::FirstMember | State::Member,
+                Token::Comma | Token::Colon,
+            ) => {}
+            (State::FirstMember | State::Member, token) => {
+                self.resync();
+                self.retry(token, span);
+            }
+            (State::AfterValue, token) if starts_value(&token) => {
+                // The ',' is missing.
+                self.state = if in_object {
+                    State::Member
+                } else {
+                    State::Value
+                };
+                self.retry(token, span);
+            }
+            (State::AfterValue, _) => self.resync(),
+            _ => {}
+        }
+    }
+
+    // Handles `token` again after recovering from it, without reporting it twice.
+    fn retry(&mut self, token: Token<'a>, span: Span) {
+        match self.step(token, span) {
+            Ok(Some(event)) => self.queue.push_back((event, span)),
+            Ok(None) => {}
+            Err(misplaced) if matches!(misplaced.error, Error::DepthLimitExceeded { .. }) => {
+                self.recover(*misplaced)
+            }
+            Err(misplaced) => self.resume(misplaced.token, misplaced.span),
+        }
+    }
+
+    // Ends the innermost container with `token` as if it was the right closing bracket,
+    // which is the likeliest intent whether a bracket is missing or mistyped.
+    fn close(&mut self, span: Span) {
+        self.insert_pending_value(span);
+        let event = self.end();
+        self.queue.push_back((event, span));
+    }
+
+    fn resync(&mut self) {
+        self.state = State::Resync;
+        self.skip_depth = 0;
+    }
+
+    // Gives a member whose key has been handed out null as its value.
+    fn insert_pending_value(&mut self, span: Span) {
+        if let State::Value | State::Colon = self.state {
+            if self.stack.last() == Some(&Container::Object) {
+                let event = self.scalar(Scalar::Null);
+                self.queue.push_back((event, span));
+            }
+        }
+    }
+}
+
+fn starts_value(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::LeftCurlyBranckt
+            | Token::LeftSquareBrancket
+            | Token::Null
+            | Token::Boolean(_)
+            | Token::Number(_)
+            | Token::String(_)
+    )
+}
+
+// Reports text after the top-level value which the tokenizer could not read as
+// `Error::TrailingContent`, since it does not matter what the text is.
+fn trailing(err: Error) -> Error {
+    let found = match &err {
+        Error::UnexpectedChar { found, .. } => format!("'{}'", found.escape_debug()),
+        // A word other than true, false or null.
+        Error::UnexpectedToken { found, .. } => found.clone(),
+        Error::UnterminatedString { .. } | Error::InvalidEscape { .. } => "a string".to_string(),
+        Error::InvalidNumber { .. } => "a number".to_string(),
+        _ => return err,
+    };
+    Error::TrailingContent {
+        found,
+        span: err.span(),
+    }
+}
+
+fn misplaced<'a>(token: Token<'a>, span: Span, expected: &str) -> Box<Misplaced<'a>> {
+    let error = match token {
+        Token::Eof => Error::UnexpectedEof {
+            expected: expected.to_string(),
+            span,
+        },
+        ref token => Error::UnexpectedToken {
+            expected: expected.to_string(),
+            found: token.to_string(),
+            span,
+        },
+    };
+    Box::new(Misplaced { token, span, error })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Severity;
+    use crate::tokenizer::TokenizerOptions;
+
+    fn events(input: &str) -> Result<Vec<Event<'_>>, Error> {
+        Reader::new(input).collect()
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn read_events() {
+        assert_eq!(events(r#"{"a": [1, "x", {}], "b": null, "c": true}"#), Ok(vec![
+            Event::StartObject,
+            Event::Key("a".into()),
+            Event::StartArray,
+            Event::Scalar(Scalar::Number("1".into())),
+            Event::Scalar(Scalar::String("x".into())),
+            Event::StartObject,
+            Event::EndObject,
+            Event::EndArray,
+            Event::Key("b".into()),
+            Event::Scalar(Scalar::Null),
+            Event::Key("c".into()),
+            Event::Scalar(Scalar::Boolean(true)),
+            Event::EndObject,
+        ]));
+        assert_eq!(events("[]"), Ok(vec![Event::StartArray, Event::EndArray]));
+        assert_eq!(events(" 1 "), Ok(vec![Event::Scalar(Scalar::Number("1".into()))]));
+    }
+
+    #[test]
+    fn read_errors_match_parser() {
+        for input in [
+            "",
+            "[1 2]",
+            "{\"a\" 1}",
+            "{\"a\": 1,}",
+            "{1: 2}",
+            "[1,]",
+            "[1",
+            "{} []",
+            "]",
+        ] {
+            let expected = crate::parse(input).unwrap_err();
+            assert_eq!(events(input), Err(expected), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn read_incrementally() {
+        let mut reader = Reader::new("[1, 2, @]");
+
+        assert_eq!(reader.next_event(), Ok(Some(Event::StartArray)));
+        assert_eq!(reader.depth(), 1);
+        assert_eq!(
+            reader.next_event(),
+            Ok(Some(Event::Scalar(Scalar::Number("1".into()))))
+        );
+        assert_eq!(reader.span().start.column, 2);
+        reader.next_event().unwrap();
+        assert!(matches!(
+            reader.next_event(),
+            Err(Error::UnexpectedChar { found: '@', .. })
+        ));
+    }
+
+    #[test]
+    fn skip_values() {
+        let mut reader =
+            Reader::new(r#"{"skip": {"a": [1, {"b": []}]}, "id": 7, "rest": [true], "more": 1}"#);
+
+        assert_eq!(reader.next_event(), Ok(Some(Event::StartObject)));
+        assert_eq!(reader.next_event(), Ok(Some(Event::Key("skip".into()))));
+        reader.skip_value().unwrap();
+        assert_eq!(reader.next_event(), Ok(Some(Event::Key("id".into()))));
+        assert_eq!(
+            reader.next_event(),
+            Ok(Some(Event::Scalar(Scalar::Number("7".into()))))
+        );
+
+        // Where a key is expected, the whole member goes.
+        reader.skip_value().unwrap();
+        assert_eq!(reader.next_event(), Ok(Some(Event::Key("more".into()))));
+        reader.skip_value().unwrap();
+
+        // Nothing is skipped at the end of a container or of the input.
+        reader.skip_value().unwrap();
+        assert_eq!(reader.next_event(), Ok(Some(Event::EndObject)));
+        reader.skip_value().unwrap();
+        assert_eq!(reader.next_event(), Ok(None));
+    }
+
+    #[test]
+    fn skip_checks_structure() {
+        let mut reader = Reader::new(r#"[{"a": [1 2]}, 3]"#);
+
+        reader.next_event().unwrap();
+        assert!(matches!(
+            reader.skip_value(),
+            Err(Error::UnexpectedToken { .. })
+        ));
+    }
+
+    #[test]
+    fn read_depth_limit() {
+        let options = ParserOptions {
+            max_depth: 2,
+            ..Default::default()
+        };
+        let events = |input| {
+            Reader::from_tokens_with_options(Tokenizer::new(input), options)
+                .collect::<Result<Vec<_>, _>>()
+        };
+
+        assert!(events("[{}]").is_ok());
+        assert!(events("[{\"a\": 1}, []]").is_ok());
+        let err = events("[{\"a\": []}]").unwrap_err();
+        assert_eq!(
+            err,
+            Error::DepthLimitExceeded {
+                limit: 2,
+                span: err.span(),
+            }
+        );
+        assert_eq!(err.span().start.column, 8);
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn read_comments() {
+        let input = "// top\n{\"a\": 1, // one\n /* b */ \"b\": [/* c */]} /* end */";
+        let options = TokenizerOptions { comments: true, ..Default::default() };
+        let tokens = || Tokenizer::with_options(input, options);
+        let comment = |text: &'static str, trailing| Event::Comment { text: text.into(), trailing };
+
+        let events = Reader::from_tokens(tokens()).collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(events, [
+            comment("// top", false), Event::StartObject, Event::Key("a".into()),
+            Event::Scalar(Scalar::Number("1".into())), comment("// one", true),
+            comment("/* b */", false), Event::Key("b".into()), Event::StartArray,
+            comment("/* c */", true), Event::EndArray, Event::EndObject, comment("/* end */", true),
+        ]);
+
+        let mut reader = Reader::from_tokens(tokens());
+        reader.skip_value().unwrap();
+        assert_eq!(reader.next_event(), Ok(Some(comment("/* end */", true))));
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn read_json5() {
+        let read = |input, json5| {
+            let tokens = Tokenizer::with_options(input, TokenizerOptions { json5, ..Default::default() });
+            let options = ParserOptions { json5, ..Default::default() };
+            Reader::from_tokens_with_options(tokens, options).collect::<Result<Vec<_>, _>>()
+        };
+        let number = |num: &str| Event::Scalar(Scalar::Number(num.to_string().into()));
+
+        assert_eq!(read("{a: [1, 2,], 'b': {},}", true).unwrap(), [
+            Event::StartObject, Event::Key("a".into()), Event::StartArray, number("1"),
+            number("2"), Event::EndArray, Event::Key("b".into()), Event::StartObject,
+            Event::EndObject, Event::EndObject,
+        ]);
+        assert!(read("[1,,]", true).is_err());
+        assert!(read("[,]", true).is_err());
+        assert!(read("{,}", true).is_err());
+        assert!(read("{a: 1 b: 2}", true).is_err());
+        assert!(read("[a]", true).is_err());
+        assert!(read("[1,]", false).is_err());
+
+        assert_eq!(read("{null: 1, true: 2, false: 3, Infinity: 4, NaN: 5}", true).unwrap(), [
+            Event::StartObject, Event::Key("null".into()), number("1"), Event::Key("true".into()),
+            number("2"), Event::Key("false".into()), number("3"), Event::Key("Infinity".into()),
+            number("4"), Event::Key("NaN".into()), number("5"), Event::EndObject,
+        ]);
+        assert!(read("{+Infinity: 1}", true).is_err());
+        assert!(read("{-Infinity: 1}", true).is_err());
+        assert!(read("{null: 1}", false).is_err());
+
+        let tokens = Tokenizer::with_options("[1, -Infinity, NaN]", TokenizerOptions { json5: true, ..Default::default() });
+        let mut reader = Reader::from_tokens_with_options(tokens, ParserOptions { json5: true, ..Default::default() });
+        assert_eq!((&mut reader).count(), 5);
+        assert_eq!(reader.diagnostics().iter().map(|d| (d.severity, d.error.span().start.offset)).collect::<Vec<_>>(), [
+            (Severity::Warning, 4), (Severity::Warning, 15),
+        ]);
+        assert!(matches!(&reader.diagnostics()[0].error, Error::NonFiniteNumber { found, .. } if found == "-Infinity"));
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn read_trailing_commas() {
+        let options = ParserOptions { trailing_commas: true, ..Default::default() };
+        let mut reader = Reader::from_tokens_with_options(Tokenizer::new("[1, {\"a\": 2,},]"), options);
+        let events = (&mut reader).collect::<Result<Vec<_>, _>>().unwrap();
+
+        assert_eq!(events.len(), 7);
+        assert_eq!(reader.diagnostics().iter().map(|d| (d.severity, d.error.span().start.offset)).collect::<Vec<_>>(), [
+            (Severity::Warning, 11), (Severity::Warning, 13),
+        ]);
+
+        let mut reader = Reader::from_tokens_with_options(Tokenizer::new("[1,,]"), options);
+        assert!(matches!(reader.nth(2), Some(Err(Error::UnexpectedToken { .. }))));
+        let mut reader = Reader::from_tokens_with_options(Tokenizer::new("{,}"), options);
+        assert!(matches!(reader.nth(1), Some(Err(Error::UnexpectedToken { .. }))));
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn read_recovering() {
+        let read = |input| {
+            let options = ParserOptions { recover: true, ..Default::default() };
+            let mut reader = Reader::from_tokens_with_options(Tokenizer::new(input), options);
+            let events = (&mut reader).collect::<Result<Vec<_>, _>>().unwrap();
+            (events, reader.diagnostics().to_vec())
+        };
+        let number = |num: &str| Event::Scalar(Scalar::Number(num.to_string().into()));
+
+        let (events, diagnostics) = read("[1 2, 01, ]");
+        assert_eq!(events, [
+            Event::StartArray, number("1"), number("2"), Event::Scalar(Scalar::Null),
+            Event::EndArray,
+        ]);
+        assert_eq!(diagnostics.len(), 3);
+        assert_eq!(diagnostics[0].error, crate::parse("[1 2, 01, ]").unwrap_err());
+        assert!(matches!(diagnostics[1].error, Error::InvalidNumber { .. }));
+
+        let (events, diagnostics) = read(r#"{"a": [1, 2}, "b" 3, 4: 5, "c":}"#);
+        assert_eq!(events, [
+            Event::StartObject, Event::Key("a".into()), Event::StartArray, number("1"),
+            number("2"), Event::EndArray, Event::Key("b".into()), number("3"),
+            Event::Key("c".into()), Event::Scalar(Scalar::Null), Event::EndObject,
+        ]);
+        assert_eq!(diagnostics.len(), 4);
+        assert!(diagnostics.iter().all(|d| d.severity == Severity::Error));
+
+        let (events, diagnostics) = read("[[1, [2, 3]], {\"a\": [[4]]}, [");
+        assert_eq!(events.len(), 19);
+        assert!(matches!(diagnostics[..], [Diagnostic { error: Error::UnexpectedEof { .. }, .. }]));
+    }
+
+    #[test]
+    fn recover_from_deep_nesting() {
+        let options = ParserOptions {
+            max_depth: 2,
+            recover: true,
+            ..Default::default()
+        };
+        let mut reader =
+            Reader::from_tokens_with_options(Tokenizer::new("[[[1, [2]], 3], 4]"), options);
+        let events = (&mut reader).collect::<Result<Vec<_>, _>>().unwrap();
+
+        assert_eq!(events.len(), 7);
+        assert_eq!(events[2], Event::Scalar(Scalar::Null));
+        assert!(matches!(
+            reader.diagnostics(),
+            [Diagnostic {
+                error: Error::DepthLimitExceeded { .. },
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn read_from_io() {
+        let input = r#"{"name": "x", "tags": ["a\nb"]}"#;
+        let events = Reader::from_reader(input.as_bytes()).collect::<Result<Vec<_>, _>>();
+
+        assert_eq!(events, Reader::new(input).collect());
+    }
+}
@@ -0,0 +1,54 @@
+/// A location in the source text. `line` and `column` start at 1 and `column` counts characters,
+/// while `offset` is the number of bytes from the beginning of the input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+    pub offset: usize,
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Self {
+            line: 1,
+            column: 1,
+            offset: 0,
+        }
+    }
+}
+
+impl Position {
+    pub fn advance(&mut self, c: char) {
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        self.offset += c.len_utf8();
+    }
+
+    pub fn advance_str(&mut self, s: &str) {
+        match s.rfind('\n') {
+            Some(i) => {
+                self.line += s.matches('\n').count();
+                self.column = 1 + s[i + 1..].chars().count();
+            }
+            None => self.column += s.chars().count(),
+        }
+        self.offset += s.len();
+    }
+}
+
+/// The half-open range `start..end` of the source text.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Span {
+    pub fn new(start: Position, end: Position) -> Self {
+        Self { start, end }
+    }
+}
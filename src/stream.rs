@@ -0,0 +1,460 @@
+//! Reading JSON text from an `io::Read` in bounded memory.
+
+use std::io::{self, Read};
+
+use crate::error::Error;
+use crate::generator::{Emitter, GeneratorOptions, IoAdapter};
+use crate::parser::{self, Node, ParserOptions};
+use crate::reader::Reader;
+use crate::span::Position;
+use crate::tokenizer::{SpannedToken, Token, TokenSource, Tokenizer, TokenizerOptions};
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+// How far past the end of a token the tokenizer may have to look to finish it, e.g. for the
+// low half of a surrogate pair.
+const LOOKAHEAD: usize = 16;
+
+/// Tokenizes JSON text read from `reader` through an internal buffer. The buffer only holds
+/// the token being read and the input after it, so its size is bounded by the longest token
+/// rather than by the whole input.
+pub struct StreamTokenizer<R> {
+    reader: R,
+    buffer: Vec<u8>,
+    // The part of `buffer` before `start` has been tokenized.
+    start: usize,
+    // The position of `buffer[start]` in the whole input.
+    position: Position,
+    eof: bool,
+    options: TokenizerOptions,
+}
+
+impl<R: Read> Iterator for StreamTokenizer<R> {
+    type Item = Result<SpannedToken<'static>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_spanned_token() {
+            Ok(SpannedToken {
+                token: Token::Eof, ..
+            }) => None,
+            result => Some(result),
+        }
+    }
+}
+
+impl<R: Read> TokenSource<'static> for StreamTokenizer<R> {
+    fn next_spanned_token(&mut self) -> Result<SpannedToken<'static>, Error> {
+        StreamTokenizer::next_spanned_token(self)
+    }
+
+    fn recover(&mut self, _err: Error) -> Result<(), Error> {
+        loop {
+            let mut tokenizer =
+                Tokenizer::resume(&self.buffer[self.start..], self.options, self.position);
+            // Fails again, which tells the tokenizer where the invalid token starts.
+            let _ = tokenizer.next_spanned_token();
+            tokenizer.skip_invalid();
+
+            if !self.eof && tokenizer.consumed() + LOOKAHEAD > self.buffer.len() - self.start {
+                self.fill()?;
+                continue;
+            }
+            self.start += tokenizer.consumed();
+            self.position = tokenizer.position();
+            return Ok(());
+        }
+    }
+}
+
+impl<R: Read> StreamTokenizer<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_options(reader, TokenizerOptions::default())
+    }
+
+    pub fn with_options(reader: R, options: TokenizerOptions) -> Self {
+        Self {
+            reader,
+            buffer: Vec::new(),
+            start: 0,
+            position: Position::default(),
+            eof: false,
+            options,
+        }
+    }
+
+    pub fn next_token(&mut self) -> Result<Token<'static>, Error> {
+        self.next_spanned_token().map(|spanned| spanned.token)
+    }
+
+    pub fn next_spanned_token(&mut self) -> Result<SpannedToken<'static>, Error> {
+        loop {
+            let mut tokenizer =
+                Tokenizer::resume(&self.buffer[self.start..], self.options, self.position);
+            let result = tokenizer.next_spanned_token();
+
+            // A token or an error which reaches the end of the buffer may only be cut off by
+            // the chunk boundary, so it is read again once more input has arrived.
+            let end = match &result {
+                Ok(_) => tokenizer.consumed(),
+                Err(err) => err.span().end.offset - self.position.offset,
+            };
+            if !self.eof && end + LOOKAHEAD > self.buffer.len() - self.start {
+                // The whitespace and comments before the token are done with, so that a long
+                // run of them does not pile up in the buffer.
+                let (skipped, position) = tokenizer.token_start();
+                self.start += skipped;
+                self.position = position;
+                self.fill()?;
+                continue;
+            }
+
+            let spanned = result?;
+            self.start += tokenizer.consumed();
+            self.position = tokenizer.position();
+            return Ok(SpannedToken {
+                token: spanned.token.into_owned(),
+                span: spanned.span,
+            });
+        }
+    }
+
+    // Drops the tokenized part of the buffer and reads at least as much as is left, so that
+    // reading a long token again and again stays linear.
+    fn fill(&mut self) -> Result<(), Error> {
+        self.buffer.drain(..self.start);
+        self.start = 0;
+
+        let len = self.buffer.len();
+        let want = len + CHUNK_SIZE.max(len);
+        self.buffer.resize(want, 0);
+
+        let mut filled = len;
+        let result = loop {
+            if filled == want {
+                break Ok(());
+            }
+            match self.reader.read(&mut self.buffer[filled..]) {
+                Ok(0) => {
+                    self.eof = true;
+                    break Ok(());
+                }
+                Ok(n) => filled += n,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => break Err(Error::io(err, self.position)),
+            }
+        };
+        self.buffer.truncate(filled);
+        result
+    }
+}
+
+/// Parses the JSON text read from `reader` without holding all of it in memory.
+pub fn parse<R: Read>(reader: R) -> Result<Node, Error> {
+    parse_with_options(
+        reader,
+        TokenizerOptions::default(),
+        ParserOptions::default(),
+    )
+}
+
+pub fn parse_with_options<R: Read>(
+    reader: R,
+    tokenizer_options: TokenizerOptions,
+    options: ParserOptions,
+) -> Result<Node, Error> {
+    parser::build(
+        StreamTokenizer::with_options(reader, tokenizer_options),
+        options,
+    )
+    .map(|parsed| parsed.node.into_owned())
+}
+
+/// Reformats the JSON text read from `reader` into `writer` as it arrives. Memory use depends
+/// on the nesting depth and the longest token, not on the size of the input. On an error,
+/// the output written so far is left in `writer`. Duplicate keys are written as they are, and
+/// warnings, e.g. about trailing commas or about Infinity and NaN becoming null, are not
+/// reported.
+pub fn format<R: Read, W: io::Write>(
+    reader: R,
+    writer: W,
+    tokenizer_options: TokenizerOptions,
+    parser_options: ParserOptions,
+    options: &GeneratorOptions,
+) -> Result<(), Error> {
+    let mut events = Reader::from_tokens_with_options(
+        StreamTokenizer::with_options(reader, tokenizer_options),
+        parser_options,
+    );
+    let mut adapter = IoAdapter::new(writer);
+    let mut emitter = Emitter::new(&mut adapter, options);
+
+    let result = events.visit(&mut emitter).and_then(|()| emitter.finish());
+    drop(emitter);
+    match result {
+        // The writer failed rather than the reader.
+        Err(Error::Io { .. }) if adapter.failed() => {
+            Err(Error::io(adapter.take_error(), events.span().end))
+        }
+        result => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::{Generator, Layout};
+
+    // Hands out the input a few bytes at a time, so that tokens span many reads.
+    struct Trickle<'a> {
+        input: &'a [u8],
+        step: usize,
+    }
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.step.min(buf.len()).min(self.input.len());
+            buf[..n].copy_from_slice(&self.input[..n]);
+            self.input = &self.input[n..];
+            Ok(n)
+        }
+    }
+
+    fn trickle(input: &str, step: usize) -> Trickle<'_> {
+        Trickle {
+            input: input.as_bytes(),
+            step,
+        }
+    }
+
+    const INPUT: &str = "{\"caf\u{e9}\": [1, -0.5e+10, true, false, null],\n \"esc\": \"\\ud83d\\ude00\\n\\\"\u{1F600}\",\n \"e\": {}, \"a\": [[]]}";
+
+    #[test]
+    fn tokenize_across_reads() {
+        let expected = Tokenizer::new(INPUT).collect::<Vec<_>>();
+        for step in [1, 2, 3, 7, 64] {
+            let tokens = StreamTokenizer::new(trickle(INPUT, step)).collect::<Vec<_>>();
+            assert_eq!(tokens, expected, "step {}", step);
+        }
+    }
+
+    #[test]
+    fn tokenize_long_string() {
+        let input = format!("[\"{}\", 1]", "\u{e9}x".repeat(CHUNK_SIZE));
+        let expected = Tokenizer::new(&input).collect::<Vec<_>>();
+        let tokens = StreamTokenizer::new(trickle(&input, 1000)).collect::<Vec<_>>();
+
+        assert_eq!(tokens, expected);
+    }
+
+    #[test]
+    fn skip_long_whitespace_in_bounded_memory() {
+        let spaces = format!("{} 1 ", " \n".repeat(2 * 1024 * 1024));
+        let comments = format!("[{}1]", "/* x */ ".repeat(512 * 1024));
+        let json5 = TokenizerOptions {
+            json5: true,
+            ..Default::default()
+        };
+
+        for (input, options) in [(&spaces, TokenizerOptions::default()), (&comments, json5)] {
+            let mut tokenizer = StreamTokenizer::with_options(trickle(input, 4096), options);
+            let tokens = (&mut tokenizer).collect::<Vec<_>>();
+
+            assert_eq!(
+                tokens,
+                Tokenizer::with_options(input, options).collect::<Vec<_>>()
+            );
+            assert!(tokenizer.buffer.capacity() <= 4 * CHUNK_SIZE);
+        }
+    }
+
+    #[test]
+    fn tokenize_errors_across_reads() {
+        for input in [
+            "[1, 01]",
+            "[\"\\ud800\\u0041\"]",
+            "[\"abc",
+            "[tru",
+            "[1.e5]",
+            "@",
+        ] {
+            let expected = Tokenizer::new(input).find_map(Result::err);
+            let error = StreamTokenizer::new(trickle(input, 1)).find_map(Result::err);
+            assert_eq!(error, expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn tokenize_read_error() {
+        struct Broken;
+
+        impl Read for Broken {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::new(io::ErrorKind::BrokenPipe, "broken pipe"))
+            }
+        }
+
+        assert!(matches!(
+            StreamTokenizer::new(Broken).next_token(),
+            Err(Error::Io {
+                kind: io::ErrorKind::BrokenPipe,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn parse_across_reads() {
+        assert_eq!(parse(trickle(INPUT, 3)), crate::parse(INPUT));
+        assert_eq!(parse(trickle("42", 1)), crate::parse("42"));
+    }
+
+    #[test]
+    fn parse_errors_match_parser() {
+        for input in [
+            "",
+            "[1 2]",
+            "{\"a\" 1}",
+            "{\"a\": 1,}",
+            "{1: 2}",
+            "[1,]",
+            "[1",
+            "{} []",
+            "]",
+        ] {
+            assert_eq!(parse(trickle(input, 1)), crate::parse(input), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn recover_across_reads() {
+        let input = format!(
+            "{{\"a\": [01x, \"b\\q\", tru], \"long\": \"{}\\u12\", \"c\" 1}}",
+            "x".repeat(CHUNK_SIZE)
+        );
+        let options = ParserOptions {
+            recover: true,
+            ..Default::default()
+        };
+        let expected = parser::build(Tokenizer::new(&input), options).unwrap();
+        for step in [1, 4096] {
+            let parsed =
+                parser::build(StreamTokenizer::new(trickle(&input, step)), options).unwrap();
+            assert_eq!(parsed.node, expected.node, "step {}", step);
+            assert_eq!(parsed.diagnostics, expected.diagnostics, "step {}", step);
+        }
+        assert_eq!(expected.diagnostics.len(), 5);
+    }
+
+    #[test]
+    fn format_matches_generator() {
+        let node = crate::parse(INPUT).unwrap();
+        let options = [
+            GeneratorOptions::default(),
+            GeneratorOptions {
+                layout: Layout::Minified,
+                final_newline: true,
+                ..Default::default()
+            },
+            GeneratorOptions {
+                inline_arrays: Some(30),
+                ..Default::default()
+            },
+            GeneratorOptions {
+                inline_arrays: Some(10),
+                ..Default::default()
+            },
+        ];
+
+        for options in options {
+            let mut output = Vec::new();
+            format(
+                trickle(INPUT, 5),
+                &mut output,
+                TokenizerOptions::default(),
+                ParserOptions::default(),
+                &options,
+            )
+            .unwrap();
+            assert_eq!(
+                String::from_utf8(output).unwrap(),
+                Generator::with_options(&node, &options).generate()
+            );
+        }
+    }
+
+    #[test]
+    fn format_comments() {
+        let input = "/* top */ [1, // one\n {\"a\": [] /* a */}\n // end\n] // after";
+        let (node, comments, _) =
+            crate::parse_with_comments(input, Default::default(), Default::default()).unwrap();
+        let options = GeneratorOptions::default();
+
+        let mut output = Vec::new();
+        format(
+            trickle(input, 2),
+            &mut output,
+            TokenizerOptions {
+                comments: true,
+                ..Default::default()
+            },
+            ParserOptions::default(),
+            &options,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            Generator::with_options(&node, &options)
+                .with_comments(&comments)
+                .generate()
+        );
+    }
+
+    #[test]
+    fn format_reports_errors() {
+        let mut output = Vec::new();
+        let err = format(
+            trickle("[1, 2] 3", 1),
+            &mut output,
+            TokenizerOptions::default(),
+            ParserOptions::default(),
+            &GeneratorOptions::default(),
+        )
+        .unwrap_err();
+
+        assert!(matches!(err, Error::TrailingContent { .. }));
+        assert_eq!(err.span().start.column, 8);
+    }
+
+    #[test]
+    fn format_reports_write_errors() {
+        struct Full;
+
+        impl io::Write for Full {
+            fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+                Err(io::Error::new(io::ErrorKind::StorageFull, "disk full"))
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let err = format(
+            trickle("\n [1]", 1),
+            Full,
+            TokenizerOptions::default(),
+            ParserOptions::default(),
+            &GeneratorOptions::default(),
+        )
+        .unwrap_err();
+
+        assert!(matches!(
+            err,
+            Error::Io {
+                kind: io::ErrorKind::StorageFull,
+                ..
+            }
+        ));
+        assert_eq!(err.span().end.line, 2);
+    }
+}
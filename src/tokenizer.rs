@@ -1,12 +1,17 @@
-use std::collections::VecDeque;
+use std::borrow::Cow;
+use std::fmt;
+use std::str;
 
-use crate::json_util;
+use crate::error::Error;
+use crate::json_util::{self, EscapeOptions};
+use crate::span::{Position, Span};
 
-#[derive(Debug, PartialEq, Eq)]
-pub enum Token {
+/// A token of JSON text. Numbers and strings without escapes borrow from the input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token<'a> {
     Null,
-    Number(String),
-    String(String),
+    Number(Cow<'a, str>),
+    String(Cow<'a, str>),
     Boolean(bool),
     Colon,
     Comma,
@@ -14,204 +19,956 @@ pub enum Token {
     RightSquareBrancket,
     LeftCurlyBranckt,
     RightCurlyBranckt,
+    /// A `//` or `/* */` comment as it is written, see `TokenizerOptions::comments`.
+    Comment(Cow<'a, str>),
+    /// An unquoted JSON5 key, see `TokenizerOptions::json5`.
+    Identifier(Cow<'a, str>),
     Eof,
 }
 
-pub struct Tokenizer {
-    input: VecDeque<char>,
+impl fmt::Display for Token<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Null => write!(f, "null"),
+            Token::Number(num) => write!(f, "number {}", num),
+            Token::String(value) => write!(
+                f,
+                "string {}",
+                json_util::quote(value, &EscapeOptions::default())
+            ),
+            Token::Boolean(b) => write!(f, "{}", b),
+            Token::Colon => write!(f, "':'"),
+            Token::Comma => write!(f, "','"),
+            Token::LeftSquareBrancket => write!(f, "'['"),
+            Token::RightSquareBrancket => write!(f, "']'"),
+            Token::LeftCurlyBranckt => write!(f, "'{{'"),
+            Token::RightCurlyBranckt => write!(f, "'}}'"),
+            Token::Comment(_) => write!(f, "a comment"),
+            Token::Identifier(name) => write!(f, "identifier {}", name),
+            Token::Eof => write!(f, "EOF"),
+        }
+    }
+}
+
+impl Token<'_> {
+    /// Copies a borrowed payload, so that the token no longer refers to the input.
+    pub fn into_owned(self) -> Token<'static> {
+        match self {
+            Token::Null => Token::Null,
+            Token::Number(num) => Token::Number(Cow::Owned(num.into_owned())),
+            Token::String(value) => Token::String(Cow::Owned(value.into_owned())),
+            Token::Boolean(b) => Token::Boolean(b),
+            Token::Colon => Token::Colon,
+            Token::Comma => Token::Comma,
+            Token::LeftSquareBrancket => Token::LeftSquareBrancket,
+            Token::RightSquareBrancket => Token::RightSquareBrancket,
+            Token::LeftCurlyBranckt => Token::LeftCurlyBranckt,
+            Token::RightCurlyBranckt => Token::RightCurlyBranckt,
+            Token::Comment(text) => Token::Comment(Cow::Owned(text.into_owned())),
+            Token::Identifier(name) => Token::Identifier(Cow::Owned(name.into_owned())),
+            Token::Eof => Token::Eof,
+        }
+    }
 }
 
-impl Iterator for Tokenizer {
-    type Item = Result<Token, String>;
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpannedToken<'a> {
+    pub token: Token<'a>,
+    pub span: Span,
+}
+
+// Tokens built without source text, e.g. in tests, get an empty span.
+impl<'a> From<Token<'a>> for SpannedToken<'a> {
+    fn from(token: Token<'a>) -> Self {
+        Self {
+            token,
+            span: Span::default(),
+        }
+    }
+}
+
+/// Hands out tokens one at a time, like `Tokenizer` over a string or `StreamTokenizer` over
+/// an `io::Read`.
+pub trait TokenSource<'a> {
+    fn next_spanned_token(&mut self) -> Result<SpannedToken<'a>, Error>;
+
+    /// Skips the input which the last call failed to tokenize with `err`, so that
+    /// tokenizing can go on. Gives `err` back if the source cannot do that.
+    fn recover(&mut self, err: Error) -> Result<(), Error> {
+        Err(err)
+    }
+}
+
+impl<'a, T: TokenSource<'a> + ?Sized> TokenSource<'a> for &mut T {
+    fn next_spanned_token(&mut self) -> Result<SpannedToken<'a>, Error> {
+        (**self).next_spanned_token()
+    }
+
+    fn recover(&mut self, err: Error) -> Result<(), Error> {
+        (**self).recover(err)
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TokenizerOptions {
+    /// Replace unpaired UTF-16 surrogates in \uXXXX escapes with U+FFFD instead of failing.
+    pub lossy_surrogates: bool,
+    /// Accept `//` and `/* */` comments as in JSONC, and hand them out as `Token::Comment`.
+    pub comments: bool,
+    /// Read JSON5: comments, which are skipped unless `comments` is set as well, unquoted keys
+    /// as `Token::Identifier`, single-quoted strings with the escapes and line continuations
+    /// of ECMAScript, hexadecimal numbers, numbers with a `+` sign or a leading or trailing
+    /// decimal point, Infinity, NaN and more whitespace. Numbers are handed out as JSON text,
+    /// e.g. `0x1F` as `31` and `.5` as `0.5`, apart from `Infinity`, `-Infinity` and `NaN`.
+    /// See also `ParserOptions::json5`.
+    pub json5: bool,
+}
+
+/// Splits JSON text into tokens. The input is read in place through a cursor.
+pub struct Tokenizer<'a> {
+    input: &'a [u8],
+    cursor: usize,
+    options: TokenizerOptions,
+    position: Position,
+    // Where the last token started, as a cursor and a position.
+    token_start: (usize, Position),
+}
+
+impl<'a> Iterator for Tokenizer<'a> {
+    type Item = Result<SpannedToken<'a>, Error>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        match self.next_token() {
-            Ok(Token::Eof) => None,
+        match self.next_spanned_token() {
+            Ok(SpannedToken {
+                token: Token::Eof, ..
+            }) => None,
             result => Some(result),
         }
     }
 }
 
-impl Tokenizer {
-    pub fn new<S: Into<String>>(input: S) -> Self {
-        let input = input.into().chars().collect::<VecDeque<char>>();
-        Self { input }
+impl<'a> TokenSource<'a> for Tokenizer<'a> {
+    fn next_spanned_token(&mut self) -> Result<SpannedToken<'a>, Error> {
+        Tokenizer::next_spanned_token(self)
+    }
+
+    fn recover(&mut self, _err: Error) -> Result<(), Error> {
+        self.skip_invalid();
+        Ok(())
+    }
+}
+
+impl<'a> Tokenizer<'a> {
+    pub fn new(input: &'a str) -> Self {
+        Self::with_options(input, TokenizerOptions::default())
     }
 
-    pub fn next_token(&mut self) -> Result<Token, String> {
-        self.skip_whitespaces();
+    pub fn with_options(input: &'a str, options: TokenizerOptions) -> Self {
+        Self::from_slice_with_options(input.as_bytes(), options)
+    }
+
+    /// Tokenizes raw bytes. Strings are checked to be valid UTF-8; everything else in JSON
+    /// text is ASCII anyway.
+    pub fn from_slice(input: &'a [u8]) -> Self {
+        Self::from_slice_with_options(input, TokenizerOptions::default())
+    }
+
+    pub fn from_slice_with_options(input: &'a [u8], options: TokenizerOptions) -> Self {
+        Self::resume(input, options, Position::default())
+    }
+
+    // Tokenizes `input` as the continuation of a larger text, in which it starts at `position`.
+    pub(crate) fn resume(input: &'a [u8], options: TokenizerOptions, position: Position) -> Self {
+        Self {
+            input,
+            cursor: 0,
+            options,
+            position,
+            token_start: (0, position),
+        }
+    }
+
+    pub(crate) fn position(&self) -> Position {
+        self.position
+    }
+
+    // The number of bytes of the input which have been tokenized.
+    pub(crate) fn consumed(&self) -> usize {
+        self.cursor
+    }
+
+    // Where the last token, or the one which failed, starts: the number of bytes before it,
+    // including the whitespace and comments skipped, and its position.
+    pub(crate) fn token_start(&self) -> (usize, Position) {
+        self.token_start
+    }
+
+    pub fn next_token(&mut self) -> Result<Token<'a>, Error> {
+        self.next_spanned_token().map(|spanned| spanned.token)
+    }
+
+    pub fn next_spanned_token(&mut self) -> Result<SpannedToken<'a>, Error> {
+        loop {
+            self.skip_whitespaces();
+
+            let start = self.position;
+            self.token_start = (self.cursor, start);
+            let token = self.tokenize()?;
+            // The comments of JSON5 are whitespace unless they are asked for.
+            if let Token::Comment(_) = token {
+                if !self.options.comments {
+                    continue;
+                }
+            }
+            return Ok(SpannedToken {
+                token,
+                span: Span::new(start, self.position),
+            });
+        }
+    }
+
+    fn tokenize(&mut self) -> Result<Token<'a>, Error> {
+        if self.options.json5 {
+            if let Some(result) = self.tokenize_json5() {
+                return result;
+            }
+        }
 
         match self.front() {
             Some(c) if c.is_ascii_digit() => self.tokenize_number(),
-            Some('-') => self.tokenize_number(),
-            Some('{') => {
+            Some(b'-') => self.tokenize_number(),
+            Some(b'{') => {
                 self.pop();
                 Ok(Token::LeftCurlyBranckt)
             }
-            Some('}') => {
+            Some(b'}') => {
                 self.pop();
                 Ok(Token::RightCurlyBranckt)
             }
-            Some('[') => {
+            Some(b'[') => {
                 self.pop();
                 Ok(Token::LeftSquareBrancket)
             }
-            Some(']') => {
+            Some(b']') => {
                 self.pop();
                 Ok(Token::RightSquareBrancket)
             }
-            Some(':') => {
+            Some(b':') => {
                 self.pop();
                 Ok(Token::Colon)
             }
-            Some(',') => {
+            Some(b',') => {
                 self.pop();
                 Ok(Token::Comma)
             }
-            Some('\"') => self.tokenize_string(),
-            Some('t') => self.tokenize_true(),
-            Some('f') => self.tokenize_false(),
-            Some('n') => self.tokenize_null(),
+            Some(b'\"') => self.tokenize_string(),
+            Some(c) if c.is_ascii_alphabetic() => self.tokenize_literal(),
+            Some(b'/') if self.options.comments || self.options.json5 => self.tokenize_comment(),
             None => Ok(Token::Eof),
-            Some(c) => Err(format!(
-                "The tokenizer found an unexpected character \'{:}\'.",
-                c
-            )),
+            Some(_) => Err(self.unexpected_char()),
+        }
+    }
+
+    // Moves the cursor from the start of the token which failed to tokenize past all of it:
+    // up to the closing quotation mark or the end of the line for a string, to the end of the
+    // line or of the input for a comment, and up to the next delimiter for anything else.
+    pub(crate) fn skip_invalid(&mut self) {
+        (self.cursor, self.position) = self.token_start;
+
+        let rest = &self.input[self.cursor..];
+        let comments = self.options.comments || self.options.json5;
+        if comments && (rest.starts_with(b"//") || rest.starts_with(b"/*")) {
+            let line = rest.starts_with(b"//");
+            while self.front().is_some_and(|c| !(line && c == b'\n')) {
+                self.skip_char();
+            }
+            return;
+        }
+
+        let quote = match self.front() {
+            Some(b'\'') if self.options.json5 => Some(b'\''),
+            Some(b'\"') => Some(b'\"'),
+            _ => None,
+        };
+        if let Some(quote) = quote {
+            self.pop();
+            loop {
+                match self.front() {
+                    None | Some(b'\n') => break,
+                    Some(c) if c == quote => {
+                        self.pop();
+                        break;
+                    }
+                    Some(b'\\') => {
+                        self.pop();
+                        if self.front() != Some(b'\n') {
+                            self.skip_char();
+                        }
+                    }
+                    Some(_) => self.skip_char(),
+                }
+            }
+            return;
+        }
+
+        self.skip_char();
+        while let Some(c) = self.front() {
+            if json_util::is_whitespace(c as char) || b"{}[]:,\"".contains(&c) {
+                break;
+            }
+            self.skip_char();
+        }
+    }
+
+    /// The input which has not been tokenized yet.
+    pub fn rest(&self) -> Cow<'a, str> {
+        String::from_utf8_lossy(&self.input[self.cursor..])
+    }
+
+    // Returns the span of the next character, or an empty span at the end of the input.
+    fn next_span(&self) -> Span {
+        let mut end = self.position;
+        match self.front_char() {
+            Some(c) => end.advance(c),
+            None if self.front().is_some() => {
+                end.column += 1;
+                end.offset += 1;
+            }
+            None => {}
+        }
+        Span::new(self.position, end)
+    }
+
+    // Reports the next character, which must exist, as unexpected.
+    fn unexpected_char(&self) -> Error {
+        match self.front_char() {
+            Some(found) => Error::UnexpectedChar {
+                found,
+                span: self.next_span(),
+            },
+            None => Error::InvalidUtf8 {
+                span: self.next_span(),
+            },
+        }
+    }
+
+    fn invalid_number<S: Into<String>>(&self, reason: S) -> Error {
+        Error::InvalidNumber {
+            reason: reason.into(),
+            span: self.next_span(),
+        }
+    }
+
+    fn invalid_escape<S: Into<String>>(&self, start: Position, reason: S) -> Error {
+        Error::InvalidEscape {
+            reason: reason.into(),
+            span: Span::new(start, self.next_span().end),
+        }
+    }
+
+    fn unterminated_string(&self, start: Position) -> Error {
+        Error::UnterminatedString {
+            span: Span::new(start, self.position),
         }
     }
 
     fn skip_whitespaces(&mut self) {
         loop {
             match self.front() {
-                Some(&c) if json_util::is_whitespace(c) => self.pop(),
+                Some(c) if json_util::is_whitespace(c as char) => {
+                    self.pop();
+                }
+                Some(_) if self.options.json5 => match self.front_char() {
+                    Some(c) if json_util::is_json5_whitespace(c) => self.skip_char(),
+                    _ => break,
+                },
                 _ => break,
-            };
+            }
         }
     }
 
-    fn tokenize_string(&mut self) -> Result<Token, String> {
-        let mut ident = String::new();
+    fn tokenize_string(&mut self) -> Result<Token<'a>, Error> {
+        // Stays `None` as long as the string has no escapes and can be borrowed.
+        let mut unescaped: Option<String> = None;
 
-        self.consume('\"')?;
+        let start = self.position;
+        self.consume(b'\"')?;
         loop {
+            let run = self.read_unescaped()?;
             match self.front() {
-                Some('\\') => {
-                    let escaped = self.pop_escape().ok_or(r#"The next of \ must be a escaped character"#)?;
-                    ident.push(escaped);
-                },
-                Some('\"') => { self.pop(); break; },
-                Some(&c) if json_util::is_unescaped(c) => {
+                Some(b'\\') => {
+                    let value = unescaped.get_or_insert_with(String::new);
+                    value.push_str(run);
+                    value.push(self.pop_escape(start)?);
+                }
+                Some(b'\"') => {
                     self.pop();
-                    ident.push(c);
-                },
-                None => return Err("The tokenizer reached EOF before finding \" which represents the end of a string".to_string()),
-                _ => return Err("The tokenizer found a unexpected character while tokenizing string.".to_string()),
+                    return Ok(Token::String(match unescaped {
+                        Some(mut value) => {
+                            value.push_str(run);
+                            Cow::Owned(value)
+                        }
+                        None => Cow::Borrowed(run),
+                    }));
+                }
+                None => return Err(self.unterminated_string(start)),
+                Some(_) => return Err(self.unexpected_char()),
+            }
+        }
+    }
+
+    // Reads the longest run of characters which can appear in a string without escaping.
+    fn read_unescaped(&mut self) -> Result<&'a str, Error> {
+        self.read_run(json_util::is_unescaped)
+    }
+
+    // Reads the longest run of bytes for which `unescaped` holds, which must be valid UTF-8.
+    fn read_run(&mut self, unescaped: impl Fn(u8) -> bool) -> Result<&'a str, Error> {
+        let input = self.input;
+        let begin = self.cursor;
+        let len = input[begin..]
+            .iter()
+            .position(|&c| !unescaped(c))
+            .unwrap_or(input.len() - begin);
+
+        match str::from_utf8(&input[begin..begin + len]) {
+            Ok(run) => {
+                self.advance_str(run);
+                Ok(run)
+            }
+            Err(err) => {
+                let valid = &input[begin..begin + err.valid_up_to()];
+                self.advance_str(str::from_utf8(valid).expect("validated above"));
+                Err(Error::InvalidUtf8 {
+                    span: self.next_span(),
+                })
+            }
+        }
+    }
+
+    // comment = "//" *( any character but a line ending ) / "/*" *( any character ) "*/"
+    fn tokenize_comment(&mut self) -> Result<Token<'a>, Error> {
+        let input = self.input;
+        let begin = self.cursor;
+        let rest = &input[begin..];
+
+        let len = match rest.get(1) {
+            Some(b'/') => {
+                let line = rest.iter().position(|&c| c == b'\n').unwrap_or(rest.len());
+                line - usize::from(line > 0 && rest[line - 1] == b'\r')
+            }
+            Some(b'*') => match rest[2..].windows(2).position(|w| w == b"*/") {
+                Some(i) => i + 4,
+                None => {
+                    let text = String::from_utf8_lossy(rest);
+                    let mut end = self.position;
+                    end.advance_str(&text);
+                    return Err(Error::UnexpectedEof {
+                        expected: "'*/'".to_string(),
+                        span: Span::new(end, end),
+                    });
+                }
+            },
+            Some(_) => {
+                self.pop();
+                return Err(self.unexpected_char());
+            }
+            None => {
+                self.pop();
+                return Err(Error::UnexpectedEof {
+                    expected: "'/' or '*'".to_string(),
+                    span: Span::new(self.position, self.position),
+                });
             }
+        };
+
+        match str::from_utf8(&rest[..len]) {
+            Ok(text) => {
+                self.advance_str(text);
+                Ok(Token::Comment(Cow::Borrowed(text)))
+            }
+            Err(err) => {
+                let valid = &rest[..err.valid_up_to()];
+                self.advance_str(str::from_utf8(valid).expect("validated above"));
+                Err(Error::InvalidUtf8 {
+                    span: self.next_span(),
+                })
+            }
+        }
+    }
+
+    fn tokenize_number(&mut self) -> Result<Token<'a>, Error> {
+        let begin = self.cursor;
+
+        self.pop_char(b'-');
+        self.read_int()?;
+        self.read_frac()?;
+        self.read_exp()?;
+
+        let number = self.lexeme(begin);
+        self.end_number(number)?;
+        Ok(Token::Number(Cow::Borrowed(number)))
+    }
+
+    // Checks that `number`, which has just been read, is not followed by more of a number.
+    fn end_number(&self, number: &str) -> Result<(), Error> {
+        match self.front() {
+            Some(c) if c.is_ascii_alphanumeric() || matches!(c, b'.' | b'+' | b'-') => Err(self
+                .invalid_number(format!(
+                    "unexpected character \'{}\' after {}",
+                    c as char, number
+                ))),
+            _ => Ok(()),
         }
+    }
 
-        Ok(Token::String(ident))
+    // Tokenizes what JSON5 reads differently from JSON, or returns `None` if the next token
+    // is the same in both.
+    fn tokenize_json5(&mut self) -> Option<Result<Token<'a>, Error>> {
+        match self.front()? {
+            quote @ (b'\"' | b'\'') => Some(self.tokenize_json5_string(quote)),
+            c if c.is_ascii_digit() || matches!(c, b'-' | b'+' | b'.') => {
+                Some(self.tokenize_json5_number())
+            }
+            _ => match self.front_char() {
+                Some(c) if json_util::is_identifier_start(c) => Some(Ok(self.tokenize_word())),
+                _ => None,
+            },
+        }
     }
 
-    fn tokenize_number(&mut self) -> Result<Token, String> {
+    // Normalises the number to JSON text, apart from Infinity and NaN.
+    fn tokenize_json5_number(&mut self) -> Result<Token<'a>, Error> {
+        let begin = self.cursor;
+        let plus = self.pop_char(b'+').is_some();
+        let minus = !plus && self.pop_char(b'-').is_some();
+        let sign = if minus { "-" } else { "" };
+
         match self.front() {
-            //Some('0') => Err("The head of number must not be zero"),
-            Some('-') => {
+            Some(b'I' | b'N') => {
+                let word = self.read_word();
+                return match word {
+                    "Infinity" if minus => Ok(Token::Number(Cow::Borrowed(self.lexeme(begin)))),
+                    "Infinity" => Ok(Token::Number(Cow::Borrowed("Infinity"))),
+                    "NaN" => Ok(Token::Number(Cow::Borrowed("NaN"))),
+                    _ => Err(self
+                        .invalid_number(format!("expected Infinity or NaN, but found {}", word))),
+                };
+            }
+            Some(b'0') if matches!(self.input.get(self.cursor + 1), Some(b'x' | b'X')) => {
                 self.pop();
-                let num = self.read_positive_number()?;
-                Ok(Token::Number(format!("-{}", num)))
+                self.pop();
+                let digits = self.cursor;
+                while self.front().is_some_and(|c| c.is_ascii_hexdigit()) {
+                    self.pop();
+                }
+                if self.cursor == digits {
+                    return Err(self.invalid_number("expected a hexadecimal digit"));
+                }
+                self.end_number(self.lexeme(begin))?;
+                let hex = self.lexeme(digits);
+                return Ok(Token::Number(Cow::Owned(format!(
+                    "{}{}",
+                    sign,
+                    json_util::hex_to_decimal(hex)
+                ))));
             }
-            _ => {
-                let num = self.read_positive_number()?;
-                Ok(Token::Number(num))
+            _ => {}
+        }
+
+        let int_begin = self.cursor;
+        if self.front() != Some(b'.') {
+            self.read_int()?;
+        }
+        let int = self.lexeme(int_begin);
+        let frac_begin = self.cursor;
+        if self.pop_char(b'.').is_some() {
+            while self.pop_digit().is_some() {}
+            if int.is_empty() && self.cursor == frac_begin + 1 {
+                return Err(self.invalid_number("expected a digit"));
             }
         }
+        let frac = self.lexeme(frac_begin);
+        let exp_begin = self.cursor;
+        self.read_exp()?;
+        let exp = self.lexeme(exp_begin);
+        self.end_number(self.lexeme(begin))?;
+
+        if !plus && !int.is_empty() && frac != "." {
+            return Ok(Token::Number(Cow::Borrowed(self.lexeme(begin))));
+        }
+        let int = if int.is_empty() { "0" } else { int };
+        let frac = if frac == "." { "" } else { frac };
+        Ok(Token::Number(Cow::Owned(format!(
+            "{}{}{}{}",
+            sign, int, frac, exp
+        ))))
     }
 
-    fn read_positive_number(&mut self) -> Result<String, String> {
-        let mut number = String::new();
+    // An identifier, or one of the words which JSON5 gives a meaning.
+    fn tokenize_word(&mut self) -> Token<'a> {
+        match self.read_word() {
+            "true" => Token::Boolean(true),
+            "false" => Token::Boolean(false),
+            "null" => Token::Null,
+            word @ ("Infinity" | "NaN") => Token::Number(Cow::Borrowed(word)),
+            word => Token::Identifier(Cow::Borrowed(word)),
+        }
+    }
 
-        while let Some(c) = self.pop_digit_or_dot() {
-            number.push(c)
+    fn read_word(&mut self) -> &'a str {
+        let input = self.input;
+        let begin = self.cursor;
+        while let Some(c) = self.front_char() {
+            if !json_util::is_identifier_part(c) {
+                break;
+            }
+            self.skip_char();
         }
+        str::from_utf8(&input[begin..self.cursor]).expect("decoded above")
+    }
+
+    // A string in single or double quotes, with the escapes of ECMAScript.
+    fn tokenize_json5_string(&mut self, quote: u8) -> Result<Token<'a>, Error> {
+        let mut unescaped: Option<String> = None;
 
-        while let Some(c) = self.pop_digit() {
-            number.push(c)
+        let start = self.position;
+        self.pop();
+        loop {
+            let run = self.read_run(|c| c != quote && !matches!(c, b'\\' | b'\n' | b'\r'))?;
+            match self.front() {
+                Some(b'\\') => {
+                    let value = unescaped.get_or_insert_with(String::new);
+                    value.push_str(run);
+                    if let Some(c) = self.pop_json5_escape(start)? {
+                        value.push(c);
+                    }
+                }
+                Some(c) if c == quote => {
+                    self.pop();
+                    return Ok(Token::String(match unescaped {
+                        Some(mut value) => {
+                            value.push_str(run);
+                            Cow::Owned(value)
+                        }
+                        None => Cow::Borrowed(run),
+                    }));
+                }
+                None => return Err(self.unterminated_string(start)),
+                Some(_) => return Err(self.unexpected_char()),
+            }
         }
+    }
 
-        number
-            .parse()
-            .map_err(|_| "digits must represent number.".to_string())
+    // Returns `None` for a line continuation.
+    fn pop_json5_escape(&mut self, string_start: Position) -> Result<Option<char>, Error> {
+        let start = self.position;
+        self.consume(b'\\')?;
+        let c = match self.front_char() {
+            Some(c) => c,
+            None if self.front().is_none() => return Err(self.unterminated_string(string_start)),
+            None => return Err(self.unexpected_char()),
+        };
+        let escaped = match c {
+            'u' => {
+                self.pop();
+                return self.pop_unicode_escape(string_start, start).map(Some);
+            }
+            'x' => {
+                self.pop();
+                let mut code = 0;
+                for _ in 0..2 {
+                    match self.front_char().and_then(|c| c.to_digit(16)) {
+                        Some(digit) => code = code * 16 + digit,
+                        None if self.front().is_none() => {
+                            return Err(self.unterminated_string(string_start))
+                        }
+                        None => {
+                            return Err(
+                                self.invalid_escape(start, "expected a hexadecimal digit in \\xHH")
+                            )
+                        }
+                    }
+                    self.pop();
+                }
+                return Ok(char::from_u32(code));
+            }
+            '0' if !self
+                .input
+                .get(self.cursor + 1)
+                .is_some_and(|c| c.is_ascii_digit()) =>
+            {
+                Some('\0')
+            }
+            '0'..='9' => {
+                return Err(self.invalid_escape(start, format!("\'\\{}\' is not a valid escape", c)))
+            }
+            '\r' => {
+                self.pop();
+                self.pop_char(b'\n');
+                return Ok(None);
+            }
+            '\n' | '\u{2028}' | '\u{2029}' => None,
+            'b' => Some('\x08'),
+            'f' => Some('\x0C'),
+            'n' => Some('\n'),
+            'r' => Some('\r'),
+            't' => Some('\t'),
+            'v' => Some('\x0B'),
+            c => Some(c),
+        };
+        self.skip_char();
+        Ok(escaped)
     }
 
-    fn tokenize_true(&mut self) -> Result<Token, String> {
-        self.consume('t')?;
-        self.consume('r')?;
-        self.consume('u')?;
-        self.consume('e')?;
+    // Returns the input from `begin` up to the cursor, which must only contain ASCII.
+    fn lexeme(&self, begin: usize) -> &'a str {
+        str::from_utf8(&self.input[begin..self.cursor]).expect("lexemes are ASCII")
+    }
 
-        Ok(Token::Boolean(true))
+    // int = zero / ( digit1-9 *DIGIT )
+    fn read_int(&mut self) -> Result<(), Error> {
+        match self.front() {
+            Some(b'0') => {
+                self.pop();
+                match self.front() {
+                    Some(c) if c.is_ascii_digit() => {
+                        Err(self.invalid_number("leading zeros are not allowed"))
+                    }
+                    _ => Ok(()),
+                }
+            }
+            Some(c) if c.is_ascii_digit() => self.read_digits(),
+            Some(_) => Err(self.invalid_number(format!(
+                "expected a digit, but found \'{}\'",
+                self.front_char().unwrap_or(char::REPLACEMENT_CHARACTER)
+            ))),
+            None => Err(self.invalid_number("expected a digit, but reached EOF")),
+        }
     }
 
-    fn tokenize_false(&mut self) -> Result<Token, String> {
-        self.consume('f')?;
-        self.consume('a')?;
-        self.consume('l')?;
-        self.consume('s')?;
-        self.consume('e')?;
+    // frac = decimal-point 1*DIGIT
+    fn read_frac(&mut self) -> Result<(), Error> {
+        if self.pop_char(b'.').is_some() {
+            self.read_digits()
+                .map_err(|_| self.invalid_number("the fraction must have at least one digit"))?;
+        }
+        Ok(())
+    }
 
-        Ok(Token::Boolean(false))
+    // exp = e [ minus / plus ] 1*DIGIT
+    fn read_exp(&mut self) -> Result<(), Error> {
+        if self
+            .pop_char(b'e')
+            .or_else(|| self.pop_char(b'E'))
+            .is_some()
+        {
+            self.pop_char(b'+').or_else(|| self.pop_char(b'-'));
+            self.read_digits()
+                .map_err(|_| self.invalid_number("the exponent must have at least one digit"))?;
+        }
+        Ok(())
     }
 
-    fn tokenize_null(&mut self) -> Result<Token, String> {
-        self.consume('n')?;
-        self.consume('u')?;
-        self.consume('l')?;
-        self.consume('l')?;
+    // 1*DIGIT
+    fn read_digits(&mut self) -> Result<(), Error> {
+        let begin = self.cursor;
+        while self.pop_digit().is_some() {}
 
-        Ok(Token::Null)
+        if self.cursor == begin {
+            Err(self.invalid_number("expected a digit"))
+        } else {
+            Ok(())
+        }
     }
 
-    fn consume(&mut self, c: char) -> Result<char, String> {
-        match self.pop() {
-            Some(top) if top == c => Ok(top),
-            Some(top) => Err(format!(
-                "The tokenizer expected {:}, but found {:}.",
-                c, top
-            )),
-            _ => Err(format!("The tokenizer expected {:}, but reached EOF.", c)),
+    // true, false or null. Any other word is read as a whole, so that a misspelled one is
+    // reported as it is written rather than at its first wrong character.
+    fn tokenize_literal(&mut self) -> Result<Token<'a>, Error> {
+        let start = self.position;
+        match self.read_word() {
+            "true" => Ok(Token::Boolean(true)),
+            "false" => Ok(Token::Boolean(false)),
+            "null" => Ok(Token::Null),
+            word => Err(Error::UnexpectedToken {
+                expected: "true, false or null".to_string(),
+                found: word.to_string(),
+                span: Span::new(start, self.position),
+            }),
         }
     }
 
-    fn front(&self) -> Option<&char> {
-        self.input.front()
+    fn consume(&mut self, c: u8) -> Result<u8, Error> {
+        match self.front() {
+            Some(top) if top == c => {
+                self.pop();
+                Ok(top)
+            }
+            Some(_) => Err(self.unexpected_char()),
+            _ => Err(Error::UnexpectedEof {
+                expected: format!("\'{}\'", c as char),
+                span: self.next_span(),
+            }),
+        }
+    }
+
+    fn front(&self) -> Option<u8> {
+        self.input.get(self.cursor).copied()
+    }
+
+    // Decodes the character at the cursor, or returns `None` at the end of the input or
+    // if it is not valid UTF-8.
+    fn front_char(&self) -> Option<char> {
+        let rest = &self.input[self.cursor..];
+        let len = match *rest.first()? {
+            c if c < 0x80 => 1,
+            c if c >= 0xF0 => 4,
+            c if c >= 0xE0 => 3,
+            _ => 2,
+        };
+        str::from_utf8(rest.get(..len)?).ok()?.chars().next()
     }
 
-    fn pop(&mut self) -> Option<char> {
-        self.input.pop_front()
+    // Moves the cursor past the next byte, which must be ASCII.
+    fn pop(&mut self) -> Option<u8> {
+        let c = self.front()?;
+        self.position.advance(c as char);
+        self.cursor += 1;
+        Some(c)
     }
 
-    fn pop_digit(&mut self) -> Option<char> {
+    // Moves the cursor past the next character, or past the next byte if it is not valid
+    // UTF-8.
+    fn skip_char(&mut self) {
+        match self.front_char() {
+            Some(c) => self.advance_str(c.encode_utf8(&mut [0; 4])),
+            None => {
+                let end = self.next_span().end;
+                self.cursor += end.offset - self.position.offset;
+                self.position = end;
+            }
+        }
+    }
+
+    fn advance_str(&mut self, s: &str) {
+        self.position.advance_str(s);
+        self.cursor += s.len();
+    }
+
+    fn pop_digit(&mut self) -> Option<u8> {
         match self.front() {
             Some(c) if c.is_ascii_digit() => self.pop(),
             _ => None,
         }
     }
 
-    fn pop_digit_or_dot(&mut self) -> Option<char> {
+    fn pop_char(&mut self, c: u8) -> Option<u8> {
         match self.front() {
-            Some(c) if c.is_ascii_digit() => self.pop(),
-            Some('.') => self.pop(),
+            Some(top) if top == c => self.pop(),
             _ => None,
         }
     }
 
-    // TODO: fix to remove the call of is_escape_target.
-    fn pop_escape(&mut self) -> Option<char> {
-        match self.front() {
-            Some(&c) if json_util::is_escape_target(c) => {
+    // `string_start` is the position of the opening quotation mark.
+    fn pop_escape(&mut self, string_start: Position) -> Result<char, Error> {
+        let start = self.position;
+        self.consume(b'\\')?;
+        match self.front_char() {
+            Some('u') => {
+                self.pop();
+                self.pop_unicode_escape(string_start, start)
+            }
+            Some(c) => {
+                let escaped = json_util::escape(c).ok_or_else(|| {
+                    self.invalid_escape(start, format!("\'\\{}\' is not a valid escape", c))
+                })?;
                 self.pop();
-                json_util::escape(c)
+                Ok(escaped)
+            }
+            None if self.front().is_none() => Err(self.unterminated_string(string_start)),
+            None => Err(self.unexpected_char()),
+        }
+    }
+
+    // Called after "\u" has been consumed. `start` is the position of the backslash.
+    fn pop_unicode_escape(
+        &mut self,
+        string_start: Position,
+        start: Position,
+    ) -> Result<char, Error> {
+        let code = self.pop_hex4(string_start, start)?;
+
+        if json_util::is_high_surrogate(code) {
+            match self.peek_unicode_escape() {
+                Some(low) if json_util::is_low_surrogate(low) => {
+                    for _ in 0..6 {
+                        self.pop();
+                    }
+                    Ok(json_util::combine_surrogates(code, low).unwrap())
+                }
+                _ => self
+                    .unpaired_surrogate(start, format!("unpaired high surrogate \\u{:04X}", code)),
             }
+        } else if json_util::is_low_surrogate(code) {
+            match self.peek_unicode_escape() {
+                Some(high) if json_util::is_high_surrogate(high) => self.unpaired_surrogate(
+                    start,
+                    format!("reversed surrogate pair \\u{:04X}\\u{:04X}", code, high),
+                ),
+                _ => self.unpaired_surrogate(
+                    start,
+                    format!(
+                        "low surrogate \\u{:04X} without a preceding high surrogate",
+                        code
+                    ),
+                ),
+            }
+        } else {
+            Ok(char::from_u32(code).unwrap())
+        }
+    }
+
+    fn unpaired_surrogate(&self, start: Position, reason: String) -> Result<char, Error> {
+        if self.options.lossy_surrogates {
+            Ok(char::REPLACEMENT_CHARACTER)
+        } else {
+            Err(Error::InvalidEscape {
+                reason,
+                span: Span::new(start, self.position),
+            })
+        }
+    }
+
+    fn pop_hex4(&mut self, string_start: Position, start: Position) -> Result<u32, Error> {
+        let mut code = 0;
+        for _ in 0..4 {
+            let digit = match self.front_char() {
+                Some(c) => c.to_digit(16).ok_or_else(|| {
+                    self.invalid_escape(
+                        start,
+                        format!(
+                            "expected a hexadecimal digit in \\uXXXX, but found \'{}\'",
+                            c
+                        ),
+                    )
+                })?,
+                None if self.front().is_none() => {
+                    return Err(self.unterminated_string(string_start))
+                }
+                None => return Err(self.unexpected_char()),
+            };
+            self.pop();
+            code = code * 16 + digit;
+        }
+        Ok(code)
+    }
+
+    // Returns the code unit of a following \uXXXX escape without consuming it.
+    fn peek_unicode_escape(&self) -> Option<u32> {
+        match self.input.get(self.cursor..self.cursor + 6)? {
+            [b'\\', b'u', hex @ ..] => hex
+                .iter()
+                .try_fold(0, |code, &c| Some(code * 16 + (c as char).to_digit(16)?)),
             _ => None,
         }
     }
@@ -220,6 +977,16 @@ impl Tokenizer {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::span::Position;
+
+    fn pos(line: usize, column: usize, offset: usize) -> Position {
+        Position {
+            line,
+            column,
+            offset,
+        }
+    }
+
     // TODO: define a function or macro to determine whether two tokens are same or not.
 
     #[test]
@@ -233,7 +1000,7 @@ mod tests {
     #[rustfmt::skip]
     fn tokenize_zero() {
         let mut tokenizer = Tokenizer::new("0");
-        assert_eq!(tokenizer.next_token(), Ok(Token::Number("0".to_string())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::Number("0".into())));
         assert_eq!(tokenizer.next_token(), Ok(Token::Eof));
     }
 
@@ -241,7 +1008,7 @@ mod tests {
     #[rustfmt::skip]
     fn tokenize_int() {
         let mut tokenizer = Tokenizer::new("123");
-        assert_eq!(tokenizer.next_token(), Ok(Token::Number("123".to_string())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::Number("123".into())));
         assert_eq!(tokenizer.next_token(), Ok(Token::Eof));
     }
 
@@ -249,7 +1016,7 @@ mod tests {
     #[rustfmt::skip]
     fn tokenize_minus_int() {
         let mut tokenizer = Tokenizer::new("-123");
-        assert_eq!(tokenizer.next_token(), Ok(Token::Number("-123".to_string())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::Number("-123".into())));
         assert_eq!(tokenizer.next_token(), Ok(Token::Eof));
     }
 
@@ -257,7 +1024,7 @@ mod tests {
     #[rustfmt::skip]
     fn tokenize_float() {
         let mut tokenizer = Tokenizer::new("3.14159265");
-        assert_eq!(tokenizer.next_token(), Ok(Token::Number("3.14159265".to_string())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::Number("3.14159265".into())));
         assert_eq!(tokenizer.next_token(), Ok(Token::Eof));
     }
 
@@ -265,7 +1032,44 @@ mod tests {
     #[rustfmt::skip]
     fn tokenize_minus_float() {
         let mut tokenizer = Tokenizer::new("-3.14159265");
-        assert_eq!(tokenizer.next_token(), Ok(Token::Number("-3.14159265".to_string())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::Number("-3.14159265".into())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::Eof));
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn tokenize_exponent() {
+        let mut tokenizer = Tokenizer::new("1e10 6.02E+23 -1.5e-3 0E0");
+        assert_eq!(tokenizer.next_token(), Ok(Token::Number("1e10".into())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::Number("6.02E+23".into())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::Number("-1.5e-3".into())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::Number("0E0".into())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::Eof));
+    }
+
+    #[test]
+    fn tokenize_invalid_numbers() {
+        for input in [
+            "01", "-01", "1.", "1.e3", ".5", "-", "-a", "1e", "1e+", "1.2.3", "1ee3", "+1", "0x10",
+        ] {
+            let mut tokenizer = Tokenizer::new(input);
+            assert!(
+                tokenizer.next_token().is_err(),
+                "{} must be rejected",
+                input
+            );
+        }
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn tokenize_number_followed_by_delimiter() {
+        let mut tokenizer = Tokenizer::new("[0,-0.0]");
+        assert_eq!(tokenizer.next_token(), Ok(Token::LeftSquareBrancket));
+        assert_eq!(tokenizer.next_token(), Ok(Token::Number("0".into())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::Comma));
+        assert_eq!(tokenizer.next_token(), Ok(Token::Number("-0.0".into())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::RightSquareBrancket));
         assert_eq!(tokenizer.next_token(), Ok(Token::Eof));
     }
 
@@ -273,7 +1077,62 @@ mod tests {
     #[rustfmt::skip]
     fn tokenize_string() {
         let mut tokenizer = Tokenizer::new(r#""apple""#);
-        assert_eq!(tokenizer.next_token(), Ok(Token::String("apple".to_string())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::String("apple".into())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::Eof));
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn tokenize_string_with_escapes() {
+        let mut tokenizer = Tokenizer::new(r#""a\"b\\c\/d\be\ff\ng\rh\ti""#);
+        assert_eq!(tokenizer.next_token(), Ok(Token::String("a\"b\\c/d\u{8}e\u{c}f\ng\rh\ti".into())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::Eof));
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn tokenize_string_with_unicode_escapes() {
+        let mut tokenizer = Tokenizer::new(r#""caf\u00e9 \u00E9 \ud83d\ude00 \u0000""#);
+        assert_eq!(tokenizer.next_token(), Ok(Token::String("caf\u{e9} \u{e9} \u{1F600} \u{0}".into())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::Eof));
+    }
+
+    #[test]
+    fn tokenize_string_with_invalid_escapes() {
+        for input in [r#""\x""#, r#""\u12""#, r#""\u12G4""#, r#""\"#] {
+            let mut tokenizer = Tokenizer::new(input);
+            assert!(
+                tokenizer.next_token().is_err(),
+                "{} must be rejected",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn tokenize_string_with_unpaired_surrogates() {
+        for input in [
+            r#""\ud83d""#,
+            r#""\ud83dx""#,
+            r#""\ud83d\u0041""#,
+            r#""\ude00""#,
+            r#""\ude00\ud83d""#,
+        ] {
+            let mut tokenizer = Tokenizer::new(input);
+            assert!(
+                tokenizer.next_token().is_err(),
+                "{} must be rejected",
+                input
+            );
+        }
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn tokenize_string_with_unpaired_surrogates_lossy() {
+        let options = TokenizerOptions { lossy_surrogates: true, ..Default::default() };
+        let mut tokenizer = Tokenizer::with_options(r#""\ud83dx\ud83d\u0041\ude00\ud83d\ude00""#, options);
+        assert_eq!(tokenizer.next_token(), Ok(Token::String("\u{FFFD}x\u{FFFD}A\u{FFFD}\u{1F600}".into())));
         assert_eq!(tokenizer.next_token(), Ok(Token::Eof));
     }
 
@@ -308,22 +1167,22 @@ mod tests {
         let mut tokenizer = Tokenizer::new(input);
         assert_eq!(tokenizer.next_token(), Ok(Token::LeftCurlyBranckt));
 
-        assert_eq!(tokenizer.next_token(), Ok(Token::String("elm1".to_string())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::String("elm1".into())));
         assert_eq!(tokenizer.next_token(), Ok(Token::Colon));
-        assert_eq!(tokenizer.next_token(), Ok(Token::Number("123".to_string())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::Number("123".into())));
         assert_eq!(tokenizer.next_token(), Ok(Token::Comma));
 
-        assert_eq!(tokenizer.next_token(), Ok(Token::String("elm2".to_string())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::String("elm2".into())));
         assert_eq!(tokenizer.next_token(), Ok(Token::Colon));
-        assert_eq!(tokenizer.next_token(), Ok(Token::Number("1.414".to_string())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::Number("1.414".into())));
         assert_eq!(tokenizer.next_token(), Ok(Token::Comma));
 
-        assert_eq!(tokenizer.next_token(), Ok(Token::String("elm3".to_string())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::String("elm3".into())));
         assert_eq!(tokenizer.next_token(), Ok(Token::Colon));
-        assert_eq!(tokenizer.next_token(), Ok(Token::String("apple".to_string())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::String("apple".into())));
         assert_eq!(tokenizer.next_token(), Ok(Token::Comma));
 
-        assert_eq!(tokenizer.next_token(), Ok(Token::String("elm4".to_string())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::String("elm4".into())));
         assert_eq!(tokenizer.next_token(), Ok(Token::Colon));
         assert_eq!(tokenizer.next_token(), Ok(Token::Boolean(false)));
 
@@ -338,22 +1197,22 @@ mod tests {
         let mut tokenizer = Tokenizer::new(input);
         assert_eq!(tokenizer.next_token(), Ok(Token::LeftCurlyBranckt));
 
-        assert_eq!(tokenizer.next_token(), Ok(Token::String("elm1".to_string())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::String("elm1".into())));
         assert_eq!(tokenizer.next_token(), Ok(Token::Colon));
-        assert_eq!(tokenizer.next_token(), Ok(Token::Number("123".to_string())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::Number("123".into())));
         assert_eq!(tokenizer.next_token(), Ok(Token::Comma));
 
-        assert_eq!(tokenizer.next_token(), Ok(Token::String("elm2".to_string())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::String("elm2".into())));
         assert_eq!(tokenizer.next_token(), Ok(Token::Colon));
-        assert_eq!(tokenizer.next_token(), Ok(Token::Number("1.414".to_string())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::Number("1.414".into())));
         assert_eq!(tokenizer.next_token(), Ok(Token::Comma));
 
-        assert_eq!(tokenizer.next_token(), Ok(Token::String("elm3".to_string())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::String("elm3".into())));
         assert_eq!(tokenizer.next_token(), Ok(Token::Colon));
-        assert_eq!(tokenizer.next_token(), Ok(Token::String("apple".to_string())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::String("apple".into())));
         assert_eq!(tokenizer.next_token(), Ok(Token::Comma));
 
-        assert_eq!(tokenizer.next_token(), Ok(Token::String("elm4".to_string())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::String("elm4".into())));
         assert_eq!(tokenizer.next_token(), Ok(Token::Colon));
         assert_eq!(tokenizer.next_token(), Ok(Token::Boolean(false)));
 
@@ -368,13 +1227,13 @@ mod tests {
         let mut tokenizer = Tokenizer::new(input);
         assert_eq!(tokenizer.next_token(), Ok(Token::LeftSquareBrancket));
 
-        assert_eq!(tokenizer.next_token(), Ok(Token::Number("123".to_string())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::Number("123".into())));
         assert_eq!(tokenizer.next_token(), Ok(Token::Comma));
 
-        assert_eq!(tokenizer.next_token(), Ok(Token::Number("1.414".to_string())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::Number("1.414".into())));
         assert_eq!(tokenizer.next_token(), Ok(Token::Comma));
 
-        assert_eq!(tokenizer.next_token(), Ok(Token::String("apple".to_string())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::String("apple".into())));
         assert_eq!(tokenizer.next_token(), Ok(Token::Comma));
 
         assert_eq!(tokenizer.next_token(), Ok(Token::Boolean(true)));
@@ -391,13 +1250,13 @@ mod tests {
 
         assert_eq!(tokenizer.next_token(), Ok(Token::LeftSquareBrancket));
 
-        assert_eq!(tokenizer.next_token(), Ok(Token::Number("123".to_string())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::Number("123".into())));
         assert_eq!(tokenizer.next_token(), Ok(Token::Comma));
 
-        assert_eq!(tokenizer.next_token(), Ok(Token::Number("1.414".to_string())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::Number("1.414".into())));
         assert_eq!(tokenizer.next_token(), Ok(Token::Comma));
 
-        assert_eq!(tokenizer.next_token(), Ok(Token::String("apple".to_string())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::String("apple".into())));
         assert_eq!(tokenizer.next_token(), Ok(Token::Comma));
 
         assert_eq!(tokenizer.next_token(), Ok(Token::Boolean(true)));
@@ -427,68 +1286,68 @@ mod tests {
             r#"}"#,
         );
 
-        let mut tokenizer = Tokenizer::new(input);
+        let mut tokenizer = Tokenizer::new(&input);
         assert_eq!(tokenizer.next_token(), Ok(Token::LeftCurlyBranckt));
-        assert_eq!(tokenizer.next_token(), Ok(Token::String("Image".to_string())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::String("Image".into())));
         assert_eq!(tokenizer.next_token(), Ok(Token::Colon));
         assert_eq!(tokenizer.next_token(), Ok(Token::LeftCurlyBranckt));
 
-        assert_eq!(tokenizer.next_token(), Ok(Token::String("Width".to_string())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::String("Width".into())));
         assert_eq!(tokenizer.next_token(), Ok(Token::Colon));
-        assert_eq!(tokenizer.next_token(), Ok(Token::Number("800".to_string())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::Number("800".into())));
         assert_eq!(tokenizer.next_token(), Ok(Token::Comma));
 
-        assert_eq!(tokenizer.next_token(), Ok(Token::String("Height".to_string())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::String("Height".into())));
         assert_eq!(tokenizer.next_token(), Ok(Token::Colon));
-        assert_eq!(tokenizer.next_token(), Ok(Token::Number("600".to_string())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::Number("600".into())));
         assert_eq!(tokenizer.next_token(), Ok(Token::Comma));
 
-        assert_eq!(tokenizer.next_token(), Ok(Token::String("Title".to_string())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::String("Title".into())));
         assert_eq!(tokenizer.next_token(), Ok(Token::Colon));
-        assert_eq!(tokenizer.next_token(), Ok(Token::String("View from 15th Floor".to_string())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::String("View from 15th Floor".into())));
         assert_eq!(tokenizer.next_token(), Ok(Token::Comma));
 
-        assert_eq!(tokenizer.next_token(), Ok(Token::String("Thumbnail".to_string())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::String("Thumbnail".into())));
         assert_eq!(tokenizer.next_token(), Ok(Token::Colon));
         assert_eq!(tokenizer.next_token(), Ok(Token::LeftCurlyBranckt));
 
-        assert_eq!(tokenizer.next_token(), Ok(Token::String("Url".to_string())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::String("Url".into())));
         assert_eq!(tokenizer.next_token(), Ok(Token::Colon));
-        assert_eq!(tokenizer.next_token(), Ok(Token::String("http://www.example.com/image/481989943".to_string())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::String("http://www.example.com/image/481989943".into())));
         assert_eq!(tokenizer.next_token(), Ok(Token::Comma));
 
-        assert_eq!(tokenizer.next_token(), Ok(Token::String("Height".to_string())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::String("Height".into())));
         assert_eq!(tokenizer.next_token(), Ok(Token::Colon));
-        assert_eq!(tokenizer.next_token(), Ok(Token::Number("125".to_string())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::Number("125".into())));
         assert_eq!(tokenizer.next_token(), Ok(Token::Comma));
 
-        assert_eq!(tokenizer.next_token(), Ok(Token::String("Width".to_string())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::String("Width".into())));
         assert_eq!(tokenizer.next_token(), Ok(Token::Colon));
-        assert_eq!(tokenizer.next_token(), Ok(Token::Number("100".to_string())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::Number("100".into())));
 
         assert_eq!(tokenizer.next_token(), Ok(Token::RightCurlyBranckt));
         assert_eq!(tokenizer.next_token(), Ok(Token::Comma));
 
-        assert_eq!(tokenizer.next_token(), Ok(Token::String("Animated".to_string())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::String("Animated".into())));
         assert_eq!(tokenizer.next_token(), Ok(Token::Colon));
         assert_eq!(tokenizer.next_token(), Ok(Token::Boolean(false)));
         assert_eq!(tokenizer.next_token(), Ok(Token::Comma));
 
-        assert_eq!(tokenizer.next_token(), Ok(Token::String("IDs".to_string())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::String("IDs".into())));
         assert_eq!(tokenizer.next_token(), Ok(Token::Colon));
 
         assert_eq!(tokenizer.next_token(), Ok(Token::LeftSquareBrancket));
 
-        assert_eq!(tokenizer.next_token(), Ok(Token::Number("116".to_string())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::Number("116".into())));
         assert_eq!(tokenizer.next_token(), Ok(Token::Comma));
 
-        assert_eq!(tokenizer.next_token(), Ok(Token::Number("943".to_string())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::Number("943".into())));
         assert_eq!(tokenizer.next_token(), Ok(Token::Comma));
 
-        assert_eq!(tokenizer.next_token(), Ok(Token::Number("234".to_string())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::Number("234".into())));
         assert_eq!(tokenizer.next_token(), Ok(Token::Comma));
 
-        assert_eq!(tokenizer.next_token(), Ok(Token::Number("38793".to_string())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::Number("38793".into())));
 
         assert_eq!(tokenizer.next_token(), Ok(Token::RightSquareBrancket));
 
@@ -496,33 +1355,233 @@ mod tests {
         assert_eq!(tokenizer.next_token(), Ok(Token::RightCurlyBranckt));
     }
 
+    #[test]
+    #[rustfmt::skip]
+    fn tokenize_spans() {
+        let mut tokenizer = Tokenizer::new("{\n  \"caf\u{e9}\": -1.5\n}");
+        assert_eq!(tokenizer.next_spanned_token(), Ok(SpannedToken { token: Token::LeftCurlyBranckt, span: Span::new(pos(1, 1, 0), pos(1, 2, 1)) }));
+        assert_eq!(tokenizer.next_spanned_token(), Ok(SpannedToken { token: Token::String("caf\u{e9}".into()), span: Span::new(pos(2, 3, 4), pos(2, 9, 11)) }));
+        assert_eq!(tokenizer.next_spanned_token(), Ok(SpannedToken { token: Token::Colon, span: Span::new(pos(2, 9, 11), pos(2, 10, 12)) }));
+        assert_eq!(tokenizer.next_spanned_token(), Ok(SpannedToken { token: Token::Number("-1.5".into()), span: Span::new(pos(2, 11, 13), pos(2, 15, 17)) }));
+        assert_eq!(tokenizer.next_spanned_token(), Ok(SpannedToken { token: Token::RightCurlyBranckt, span: Span::new(pos(3, 1, 18), pos(3, 2, 19)) }));
+        assert_eq!(tokenizer.next_spanned_token(), Ok(SpannedToken { token: Token::Eof, span: Span::new(pos(3, 2, 19), pos(3, 2, 19)) }));
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn tokenize_error_position() {
+        let mut tokenizer = Tokenizer::new("[\n  1,\n  x]");
+        assert_eq!(tokenizer.next_token(), Ok(Token::LeftSquareBrancket));
+        assert_eq!(tokenizer.next_token(), Ok(Token::Number("1".into())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::Comma));
+        assert_eq!(tokenizer.next_token().map_err(|err| err.span()), Err(Span::new(pos(3, 3, 9), pos(3, 4, 10))));
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn tokenize_surrogate_error_position() {
+        let mut tokenizer = Tokenizer::new(r#""ab\ud800""#);
+        assert_eq!(tokenizer.next_token().map_err(|err| err.span()), Err(Span::new(pos(1, 4, 3), pos(1, 10, 9))));
+    }
+
+    #[test]
+    fn tokenize_borrows_unless_escaped() {
+        let mut tokenizer = Tokenizer::new(r#"["plain", "esc\naped", -1.5e3]"#);
+        tokenizer.next_token().unwrap();
+
+        assert!(matches!(
+            tokenizer.next_token(),
+            Ok(Token::String(Cow::Borrowed("plain")))
+        ));
+        tokenizer.next_token().unwrap();
+        assert!(
+            matches!(tokenizer.next_token(), Ok(Token::String(Cow::Owned(value))) if value == "esc\naped")
+        );
+        tokenizer.next_token().unwrap();
+        assert!(matches!(
+            tokenizer.next_token(),
+            Ok(Token::Number(Cow::Borrowed("-1.5e3")))
+        ));
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn tokenize_slice() {
+        let mut tokenizer = Tokenizer::from_slice("[\"caf\u{e9}\"]".as_bytes());
+        assert_eq!(tokenizer.next_token(), Ok(Token::LeftSquareBrancket));
+        assert_eq!(tokenizer.next_token(), Ok(Token::String("caf\u{e9}".into())));
+        assert_eq!(tokenizer.next_token(), Ok(Token::RightSquareBrancket));
+        assert_eq!(tokenizer.next_token(), Ok(Token::Eof));
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn tokenize_invalid_utf8() {
+        let mut tokenizer = Tokenizer::from_slice(b"\"ab\xE9c\"");
+        assert_eq!(tokenizer.next_token(), Err(Error::InvalidUtf8 { span: Span::new(pos(1, 4, 3), pos(1, 5, 4)) }));
+
+        let mut tokenizer = Tokenizer::from_slice(b"[\xFF]");
+        assert_eq!(tokenizer.next_token(), Ok(Token::LeftSquareBrancket));
+        assert_eq!(tokenizer.next_token(), Err(Error::InvalidUtf8 { span: Span::new(pos(1, 2, 1), pos(1, 3, 2)) }));
+
+        let mut tokenizer = Tokenizer::from_slice("[\u{e9}]".as_bytes());
+        assert_eq!(tokenizer.next_token(), Ok(Token::LeftSquareBrancket));
+        assert_eq!(tokenizer.next_token(), Err(Error::UnexpectedChar { found: '\u{e9}', span: Span::new(pos(1, 2, 1), pos(1, 3, 3)) }));
+    }
+
+    #[test]
+    fn tokenize_error_kinds() {
+        fn next(input: &str) -> Result<Token<'_>, Error> {
+            Tokenizer::new(input).next_token()
+        }
+
+        assert!(matches!(
+            next("@"),
+            Err(Error::UnexpectedChar { found: '@', .. })
+        ));
+        assert!(matches!(
+            next("\"a\u{1}\""),
+            Err(Error::UnexpectedChar { found: '\u{1}', .. })
+        ));
+        assert!(matches!(
+            next("\"abc"),
+            Err(Error::UnterminatedString { .. })
+        ));
+        assert!(matches!(
+            next("\"\\u12"),
+            Err(Error::UnterminatedString { .. })
+        ));
+        assert!(matches!(next(r#""\x""#), Err(Error::InvalidEscape { .. })));
+        assert!(matches!(
+            next(r#""\ude00""#),
+            Err(Error::InvalidEscape { .. })
+        ));
+        assert!(matches!(next("01"), Err(Error::InvalidNumber { .. })));
+        assert!(matches!(next("1.e5"), Err(Error::InvalidNumber { .. })));
+        assert!(matches!(
+            next("tru"),
+            Err(Error::UnexpectedToken { found, .. }) if found == "tru"
+        ));
+        assert_eq!(
+            next("trxe, 1"),
+            Err(Error::UnexpectedToken {
+                expected: "true, false or null".to_string(),
+                found: "trxe".to_string(),
+                span: Span::new(pos(1, 1, 0), pos(1, 5, 4)),
+            })
+        );
+        assert!(matches!(next("nullx"), Err(Error::UnexpectedToken { .. })));
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn tokenize_comments() {
+        let options = TokenizerOptions { comments: true, ..Default::default() };
+        let input = "// first\r\n[1, /* a\n b */ 2] //";
+        let tokens = Tokenizer::with_options(input, options).collect::<Result<Vec<_>, _>>().unwrap();
+
+        assert_eq!(tokens.iter().map(|spanned| spanned.token.clone()).collect::<Vec<_>>(), [
+            Token::Comment("// first".into()), Token::LeftSquareBrancket, Token::Number("1".into()),
+            Token::Comma, Token::Comment("/* a\n b */".into()), Token::Number("2".into()),
+            Token::RightSquareBrancket, Token::Comment("//".into()),
+        ]);
+        assert_eq!(tokens[4].span, Span::new(pos(2, 5, 14), pos(3, 6, 24)));
+
+        let next = |input| Tokenizer::with_options(input, options).next_token();
+        assert!(matches!(next("/* a"), Err(Error::UnexpectedEof { .. })));
+        assert!(matches!(next("/ a"), Err(Error::UnexpectedChar { found: ' ', span }) if span.start.offset == 1));
+        assert!(matches!(next("/"), Err(Error::UnexpectedEof { span, .. }) if span.start.offset == 1));
+        let mut tokenizer = Tokenizer::with_options("[1] /", options);
+        assert!(matches!(tokenizer.nth(3), Some(Err(Error::UnexpectedEof { .. }))));
+        assert!(matches!(Tokenizer::new("// a").next_token(), Err(Error::UnexpectedChar { found: '/', .. })));
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn tokenize_json5() {
+        let options = TokenizerOptions { json5: true, ..Default::default() };
+        let tokens = |input| {
+            Tokenizer::with_options(input, options)
+                .map(|spanned| spanned.map(|spanned| spanned.token))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let number = |num: &'static str| Token::Number(num.into());
+
+        assert_eq!(tokens("0x1F -0XaB .5 5. +1 1e3 Infinity -Infinity NaN").unwrap(), [
+            number("31"), number("-171"), number("0.5"), number("5"), number("1"),
+            number("1e3"), number("Infinity"), number("-Infinity"), number("NaN"),
+        ]);
+        assert_eq!(tokens(r#"{a_1: 'it\'s "x"', $b: "\x41\0\v\q\\"} // c"#).unwrap(), [
+            Token::LeftCurlyBranckt, Token::Identifier("a_1".into()), Token::Colon,
+            Token::String("it's \"x\"".into()), Token::Comma, Token::Identifier("$b".into()),
+            Token::Colon, Token::String("A\0\u{b}q\\".into()), Token::RightCurlyBranckt,
+        ]);
+        assert_eq!(tokens("'a\\\nb'").unwrap(), [Token::String("ab".into())]);
+        assert_eq!(tokens("\u{a0}\u{feff}true\u{2028}null").unwrap(), [Token::Boolean(true), Token::Null]);
+
+        let with_comments = TokenizerOptions { comments: true, ..options };
+        assert_eq!(Tokenizer::with_options("/* c */ 1", with_comments).next_token(), Ok(Token::Comment("/* c */".into())));
+
+        for input in ["0x", "+-1", ".", "'a", "'\\1'", "01"] {
+            assert!(tokens(input).is_err(), "{}", input);
+        }
+        assert!(Tokenizer::new("'a'").next_token().is_err());
+        assert!(Tokenizer::new("a").next_token().is_err());
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn skip_invalid_tokens() {
+        let input = b"[01x, \"a\\q\\\"b\", tru}, \"x\x01\n2, @@ 3, \xE9]";
+        let mut tokenizer = Tokenizer::from_slice(input);
+        let mut tokens = Vec::new();
+        let mut errors = 0;
+        loop {
+            match tokenizer.next_token() {
+                Ok(Token::Eof) => break,
+                Ok(token) => tokens.push(token),
+                Err(err) => {
+                    errors += 1;
+                    tokenizer.recover(err).unwrap();
+                }
+            }
+        }
+
+        assert_eq!(errors, 6);
+        assert_eq!(tokens, [
+            Token::LeftSquareBrancket, Token::Comma, Token::Comma, Token::RightCurlyBranckt,
+            Token::Comma, Token::Number("2".into()), Token::Comma, Token::Number("3".into()),
+            Token::Comma, Token::RightSquareBrancket,
+        ]);
+    }
+
     #[test]
     #[rustfmt::skip]
     fn tokenzie_with_iterator() {
         let input = r#"{ "elm1" : 123, "elm2" : 456 , "elm3" : "apple", "elm4": false }"#;
         let mut tokenizer = Tokenizer::new(input);
-        assert_eq!(tokenizer.next(), Some(Ok(Token::LeftCurlyBranckt)));
+        assert_eq!(tokenizer.next().map(|t| t.map(|t| t.token)), Some(Ok(Token::LeftCurlyBranckt)));
 
-        assert_eq!(tokenizer.next(), Some(Ok(Token::String("elm1".to_string()))));
-        assert_eq!(tokenizer.next(), Some(Ok(Token::Colon)));
-        assert_eq!(tokenizer.next(), Some(Ok(Token::Number("123".to_string()))));
-        assert_eq!(tokenizer.next(), Some(Ok(Token::Comma)));
+        assert_eq!(tokenizer.next().map(|t| t.map(|t| t.token)), Some(Ok(Token::String("elm1".into()))));
+        assert_eq!(tokenizer.next().map(|t| t.map(|t| t.token)), Some(Ok(Token::Colon)));
+        assert_eq!(tokenizer.next().map(|t| t.map(|t| t.token)), Some(Ok(Token::Number("123".into()))));
+        assert_eq!(tokenizer.next().map(|t| t.map(|t| t.token)), Some(Ok(Token::Comma)));
 
-        assert_eq!(tokenizer.next(), Some(Ok(Token::String("elm2".to_string()))));
-        assert_eq!(tokenizer.next(), Some(Ok(Token::Colon)));
-        assert_eq!(tokenizer.next(), Some(Ok(Token::Number("456".to_string()))));
-        assert_eq!(tokenizer.next(), Some(Ok(Token::Comma)));
+        assert_eq!(tokenizer.next().map(|t| t.map(|t| t.token)), Some(Ok(Token::String("elm2".into()))));
+        assert_eq!(tokenizer.next().map(|t| t.map(|t| t.token)), Some(Ok(Token::Colon)));
+        assert_eq!(tokenizer.next().map(|t| t.map(|t| t.token)), Some(Ok(Token::Number("456".into()))));
+        assert_eq!(tokenizer.next().map(|t| t.map(|t| t.token)), Some(Ok(Token::Comma)));
 
-        assert_eq!(tokenizer.next(), Some(Ok(Token::String("elm3".to_string()))));
-        assert_eq!(tokenizer.next(), Some(Ok(Token::Colon)));
-        assert_eq!(tokenizer.next(), Some(Ok(Token::String("apple".to_string()))));
-        assert_eq!(tokenizer.next(), Some(Ok(Token::Comma)));
+        assert_eq!(tokenizer.next().map(|t| t.map(|t| t.token)), Some(Ok(Token::String("elm3".into()))));
+        assert_eq!(tokenizer.next().map(|t| t.map(|t| t.token)), Some(Ok(Token::Colon)));
+        assert_eq!(tokenizer.next().map(|t| t.map(|t| t.token)), Some(Ok(Token::String("apple".into()))));
+        assert_eq!(tokenizer.next().map(|t| t.map(|t| t.token)), Some(Ok(Token::Comma)));
 
-        assert_eq!(tokenizer.next(), Some(Ok(Token::String("elm4".to_string()))));
-        assert_eq!(tokenizer.next(), Some(Ok(Token::Colon)));
-        assert_eq!(tokenizer.next(), Some(Ok(Token::Boolean(false))));
+        assert_eq!(tokenizer.next().map(|t| t.map(|t| t.token)), Some(Ok(Token::String("elm4".into()))));
+        assert_eq!(tokenizer.next().map(|t| t.map(|t| t.token)), Some(Ok(Token::Colon)));
+        assert_eq!(tokenizer.next().map(|t| t.map(|t| t.token)), Some(Ok(Token::Boolean(false))));
 
-        assert_eq!(tokenizer.next(), Some(Ok(Token::RightCurlyBranckt)));
-        assert_eq!(tokenizer.next(), None);
+        assert_eq!(tokenizer.next().map(|t| t.map(|t| t.token)), Some(Ok(Token::RightCurlyBranckt)));
+        assert_eq!(tokenizer.next().map(|t| t.map(|t| t.token)), None);
     }
 }
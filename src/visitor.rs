@@ -0,0 +1,441 @@
+//! Push parsing: the structure of a JSON text is handed to a `Visitor` as it is read,
+//! without building a tree.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use crate::comments::{self, Attached, Comment, Comments};
+use crate::error::Error;
+use crate::parser::{Node, ParserOptions};
+use crate::reader::Reader;
+use crate::span::Span;
+use crate::tokenizer::TokenSource;
+
+/// Receives the constructs of a JSON text in the order in which they appear. Every method
+/// does nothing by default, so that a visitor only implements what it is interested in.
+/// An error returned by any method stops the visit and is passed on to the caller.
+pub trait Visitor<'a> {
+    /// The error with which the visitor stops the visit. Errors in the JSON text are converted
+    /// into it, so that the caller gets either.
+    type Error: From<Error>;
+
+    /// Receives the span of the token behind the construct which is handed over next.
+    fn span(&mut self, _span: Span) {}
+
+    fn begin_object(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn end_object(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// The name of the member whose value comes next.
+    fn key(&mut self, _key: Cow<'a, str>) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn begin_array(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn end_array(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn string(&mut self, _value: Cow<'a, str>) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// The number as it is written in the source text.
+    fn number(&mut self, _num: Cow<'a, str>) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn boolean(&mut self, _b: bool) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn null(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// A comment of JSONC text as it is written, see `TokenizerOptions::comments`.
+    /// `trailing` is whether it follows other tokens on the same line.
+    fn comment(&mut self, _text: Cow<'a, str>, _trailing: bool) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// Reads a whole JSON text from `tokens` and hands it to `visitor`. The text is checked as
+/// it goes, so the visitor may already have seen part of it when an error is returned.
+pub fn visit<'a, S: TokenSource<'a>, V: Visitor<'a>>(
+    tokens: S,
+    visitor: &mut V,
+) -> Result<(), V::Error> {
+    visit_with_options(tokens, ParserOptions::default(), visitor)
+}
+
+/// Reads a whole JSON text from `tokens` like `visit`, with `options`. Warnings, and the
+/// syntax errors read past with `ParserOptions::recover`, are only available from
+/// `Reader::diagnostics` after `Reader::visit`.
+pub fn visit_with_options<'a, S: TokenSource<'a>, V: Visitor<'a>>(
+    tokens: S,
+    options: ParserOptions,
+    visitor: &mut V,
+) -> Result<(), V::Error> {
+    Reader::from_tokens_with_options(tokens, options).visit(visitor)
+}
+
+/// Hands the tree under `node` to `visitor` as if it was being read. The tree is walked with
+/// an explicit stack, so that its depth is bounded by the heap rather than by the call stack.
+pub fn walk<'a, V: Visitor<'a>>(node: &'a Node, visitor: &mut V) -> Result<(), V::Error> {
+    walk_tree(node, None, visitor)
+}
+
+/// Walks the tree under `node` like `walk`, handing `comments` to `visitor` next to the
+/// values to which they are attached.
+pub fn walk_with_comments<'a, V: Visitor<'a>>(
+    node: &'a Node,
+    comments: &'a Comments,
+    visitor: &mut V,
+) -> Result<(), V::Error> {
+    walk_tree(
+        node,
+        Some(comments).filter(|comments| !comments.is_empty()),
+        visitor,
+    )
+}
+
+fn walk_tree<'a, V: Visitor<'a>>(
+    node: &'a Node,
+    comments: Option<&'a Comments>,
+    visitor: &mut V,
+) -> Result<(), V::Error> {
+    let mut attached = AttachedComments {
+        comments,
+        pointer: String::new(),
+    };
+    // The containers being walked, each with the children which have not been visited yet
+    // and the length of its pointer.
+    let mut stack = Vec::new();
+    let mut next = Some(node);
+    attached.visit(visitor, |a| &a.before)?;
+
+    loop {
+        if let Some(node) = next.take() {
+            match node {
+                Node::Null => visitor.null()?,
+                Node::Number(num) => visitor.number(Cow::Borrowed(num))?,
+                Node::String(value) => visitor.string(Cow::Borrowed(value))?,
+                Node::Boolean(b) => visitor.boolean(*b)?,
+                Node::Object(kvm) => {
+                    visitor.begin_object()?;
+                    stack.push((Children::Object(kvm.iter()), attached.pointer.len()));
+                }
+                Node::MultiObject(members) => {
+                    visitor.begin_object()?;
+                    stack.push((
+                        Children::MultiObject(members.iter(), HashMap::new()),
+                        attached.pointer.len(),
+                    ));
+                }
+                Node::Array(values) => {
+                    visitor.begin_array()?;
+                    let children = Children::Array(values.iter().enumerate());
+                    stack.push((children, attached.pointer.len()));
+                }
+            }
+            if !matches!(
+                node,
+                Node::Object(_) | Node::MultiObject(_) | Node::Array(_)
+            ) {
+                attached.visit(visitor, |a| &a.after)?;
+            }
+        }
+
+        let (children, len) = match stack.last_mut() {
+            Some((children, len)) => (children, *len),
+            None => return Ok(()),
+        };
+        let child = match children {
+            Children::Object(members) => members
+                .next()
+                .map(|(key, node)| (Segment::Key(key, 0), node)),
+            Children::MultiObject(members, seen) => members.next().map(|(key, node)| {
+                let occurrence = seen.entry(key.as_str()).or_insert(0);
+                *occurrence += 1;
+                (Segment::Key(key, *occurrence - 1), node)
+            }),
+            Children::Array(values) => values.next().map(|(i, node)| (Segment::Index(i), node)),
+        };
+        match child {
+            Some((segment, node)) => {
+                attached.enter(len, &segment);
+                attached.visit(visitor, |a| &a.before)?;
+                if let Segment::Key(key, _) = segment {
+                    visitor.key(Cow::Borrowed(key))?;
+                }
+                next = Some(node);
+            }
+            None => {
+                let array = matches!(children, Children::Array(_));
+                stack.pop();
+                attached.pointer.truncate(len);
+                attached.visit(visitor, |a| &a.end)?;
+                if array {
+                    visitor.end_array()?;
+                } else {
+                    visitor.end_object()?;
+                }
+                attached.visit(visitor, |a| &a.after)?;
+            }
+        }
+    }
+}
+
+enum Children<'a> {
+    Object(indexmap::map::Iter<'a, String, Node>),
+    // With how many times each key has been seen so far.
+    MultiObject(
+        std::slice::Iter<'a, (String, Node)>,
+        HashMap<&'a str, usize>,
+    ),
+    Array(std::iter::Enumerate<std::slice::Iter<'a, Node>>),
+}
+
+enum Segment<'a> {
+    // A key with the number of members before it which have the same key.
+    Key(&'a str, usize),
+    Index(usize),
+}
+
+// Hands the comments attached to the value at `pointer` to a visitor. The pointer is only kept
+// up to date when there are comments.
+struct AttachedComments<'a> {
+    comments: Option<&'a Comments>,
+    pointer: String,
+}
+
+impl<'a> AttachedComments<'a> {
+    // Moves the pointer to a child of the container whose pointer is `len` bytes long.
+    fn enter(&mut self, len: usize, segment: &Segment) {
+        if self.comments.is_none() {
+            return;
+        }
+        self.pointer.truncate(len);
+        match segment {
+            Segment::Key(key, occurrence) => {
+                comments::push_key(&mut self.pointer, key, *occurrence)
+            }
+            Segment::Index(i) => comments::push_segment(&mut self.pointer, &i.to_string()),
+        }
+    }
+
+    fn visit<V: Visitor<'a>>(
+        &self,
+        visitor: &mut V,
+        which: fn(&'a Attached) -> &'a Vec<Comment>,
+    ) -> Result<(), V::Error> {
+        let attached = match self
+            .comments
+            .and_then(|comments| comments.get(&self.pointer))
+        {
+            Some(attached) => attached,
+            None => return Ok(()),
+        };
+        for comment in which(attached) {
+            visitor.comment(Cow::Borrowed(&comment.text), comment.trailing)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenizer::Tokenizer;
+
+    // Writes down every callback it receives.
+    #[derive(Default)]
+    struct Recorder {
+        calls: Vec<String>,
+    }
+
+    impl<'a> Visitor<'a> for Recorder {
+        type Error = Error;
+
+        fn begin_object(&mut self) -> Result<(), Error> {
+            self.calls.push("{".to_string());
+            Ok(())
+        }
+
+        fn end_object(&mut self) -> Result<(), Error> {
+            self.calls.push("}".to_string());
+            Ok(())
+        }
+
+        fn key(&mut self, key: Cow<'a, str>) -> Result<(), Error> {
+            self.calls.push(format!("key {}", key));
+            Ok(())
+        }
+
+        fn begin_array(&mut self) -> Result<(), Error> {
+            self.calls.push("[".to_string());
+            Ok(())
+        }
+
+        fn end_array(&mut self) -> Result<(), Error> {
+            self.calls.push("]".to_string());
+            Ok(())
+        }
+
+        fn string(&mut self, value: Cow<'a, str>) -> Result<(), Error> {
+            self.calls.push(format!("string {}", value));
+            Ok(())
+        }
+
+        fn number(&mut self, num: Cow<'a, str>) -> Result<(), Error> {
+            self.calls.push(format!("number {}", num));
+            Ok(())
+        }
+
+        fn boolean(&mut self, b: bool) -> Result<(), Error> {
+            self.calls.push(format!("boolean {}", b));
+            Ok(())
+        }
+
+        fn null(&mut self) -> Result<(), Error> {
+            self.calls.push("null".to_string());
+            Ok(())
+        }
+    }
+
+    const INPUT: &str = r#"{"a": [1, "x\n", {}], "b": null, "c": [true]}"#;
+
+    #[test]
+    #[rustfmt::skip]
+    fn visit_text() {
+        let mut recorder = Recorder::default();
+        visit(Tokenizer::new(INPUT), &mut recorder).unwrap();
+
+        assert_eq!(recorder.calls, [
+            "{", "key a", "[", "number 1", "string x\n", "{", "}", "]",
+            "key b", "null", "key c", "[", "boolean true", "]", "}",
+        ]);
+    }
+
+    #[test]
+    fn visit_reports_errors() {
+        let mut recorder = Recorder::default();
+        let err = visit(Tokenizer::new("[1, 2 3]"), &mut recorder).unwrap_err();
+
+        assert_eq!(err, crate::parse("[1, 2 3]").unwrap_err());
+        assert_eq!(recorder.calls, ["[", "number 1", "number 2"]);
+
+        let mut recorder = Recorder::default();
+        assert!(matches!(
+            visit(Tokenizer::new("[1] 2"), &mut recorder),
+            Err(Error::TrailingContent { .. })
+        ));
+    }
+
+    #[test]
+    fn visitor_errors_stop_the_visit() {
+        // Counts the numbers and gives up after the second one.
+        struct Limit(usize);
+
+        #[derive(Debug, PartialEq)]
+        enum LimitError {
+            TooMany,
+            Json(Error),
+        }
+
+        impl From<Error> for LimitError {
+            fn from(err: Error) -> Self {
+                LimitError::Json(err)
+            }
+        }
+
+        impl Visitor<'_> for Limit {
+            type Error = LimitError;
+
+            fn number(&mut self, _: Cow<str>) -> Result<(), LimitError> {
+                self.0 += 1;
+                if self.0 == 2 {
+                    return Err(LimitError::TooMany);
+                }
+                Ok(())
+            }
+        }
+
+        let mut limit = Limit(0);
+        let err = visit(Tokenizer::new("[1, 2, 3, @]"), &mut limit).unwrap_err();
+        assert_eq!(err, LimitError::TooMany);
+        assert_eq!(limit.0, 2);
+
+        let mut limit = Limit(0);
+        let err = visit(Tokenizer::new("[1 @]"), &mut limit).unwrap_err();
+        assert!(matches!(
+            err,
+            LimitError::Json(Error::UnexpectedChar { .. })
+        ));
+    }
+
+    #[test]
+    fn visit_with_options_reads_json5() {
+        let options = ParserOptions {
+            json5: true,
+            max_depth: 2,
+            ..Default::default()
+        };
+        let tokenizer_options = crate::TokenizerOptions {
+            json5: true,
+            ..Default::default()
+        };
+        let input = "{a: [1,], // one\n}";
+
+        let mut recorder = Recorder::default();
+        let tokens = Tokenizer::with_options(input, tokenizer_options);
+        visit_with_options(tokens, options, &mut recorder).unwrap();
+        assert_eq!(recorder.calls, ["{", "key a", "[", "number 1", "]", "}"]);
+
+        let options = ParserOptions {
+            max_depth: 1,
+            ..options
+        };
+        let tokens = Tokenizer::with_options(input, tokenizer_options);
+        assert!(matches!(
+            visit_with_options(tokens, options, &mut Recorder::default()),
+            Err(Error::DepthLimitExceeded { limit: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn walk_tree() {
+        let mut visited = Recorder::default();
+        visit(Tokenizer::new(INPUT), &mut visited).unwrap();
+
+        let node = crate::parse(INPUT).unwrap();
+        let mut walked = Recorder::default();
+        walk(&node, &mut walked).unwrap();
+
+        assert_eq!(walked.calls, visited.calls);
+    }
+
+    #[test]
+    fn walk_deep_tree() {
+        let mut node = Node::Null;
+        for _ in 0..100_000 {
+            node = Node::Array(vec![node]);
+        }
+
+        let output = crate::to_string(&node);
+        node.drop_deep();
+
+        assert_eq!(
+            output,
+            format!("{}null{}", "[".repeat(100_000), "]".repeat(100_000))
+        );
+    }
+}
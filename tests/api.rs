@@ -0,0 +1,287 @@
+use std::borrow::Cow;
+
+use json_pretty::{
+    cst, parse, parse_borrowed, parse_first, parse_with_comments, parse_with_diagnostics,
+    parse_with_options, to_string, to_string_pretty, to_writer, visitor, Error, EscapeOptions,
+    Event, Generator, GeneratorOptions, Node, ParserOptions, Reader, Scalar, Severity, Tokenizer,
+    TokenizerOptions, Visitor,
+};
+
+#[test]
+fn parse_and_access_values() {
+    let node = parse(
+        r#"{"name": "json-pretty", "version": 1.5, "tags": ["a", "b"], "ok": true, "none": null}"#,
+    )
+    .unwrap();
+
+    assert_eq!(node.get("name").and_then(Node::as_str), Some("json-pretty"));
+    assert_eq!(node.get("version").and_then(Node::as_f64), Some(1.5));
+    assert_eq!(node.get("version").and_then(Node::as_number), Some("1.5"));
+    assert_eq!(
+        node.get("tags")
+            .and_then(|tags| tags.get_index(1))
+            .and_then(Node::as_str),
+        Some("b")
+    );
+    assert_eq!(node.get("ok").and_then(Node::as_bool), Some(true));
+    assert!(node.get("none").is_some_and(Node::is_null));
+    assert_eq!(node.get("missing"), None);
+    assert_eq!(node.as_object().map(|kvm| kvm.len()), Some(5));
+
+    let kvm = match node {
+        Node::Object(kvm) => kvm,
+        _ => unreachable!(),
+    };
+    assert_eq!(kvm.into_keys().next().as_deref(), Some("name"));
+}
+
+#[test]
+fn drop_deep_trees() {
+    let options = ParserOptions {
+        max_depth: usize::MAX,
+        ..Default::default()
+    };
+    let input = format!("{}{}", "[".repeat(200_000), "]".repeat(200_000));
+    parse_with_options(&input, Default::default(), options)
+        .unwrap()
+        .drop_deep();
+
+    // The tree read before the error is dropped by the parser.
+    let input = format!("{} 1", input);
+    assert!(matches!(
+        parse_with_options(&input, Default::default(), options),
+        Err(Error::TrailingContent { .. })
+    ));
+}
+
+#[test]
+fn parse_reports_errors() {
+    let err = parse("[1, 2,]").unwrap_err();
+
+    assert_eq!(err.span().start.column, 7);
+    assert!(matches!(err, json_pretty::Error::UnexpectedToken { .. }));
+
+    let err = parse(r#"{"a": 1} xyz"#).unwrap_err();
+    assert_eq!(err.span().start.column, 10);
+    assert!(matches!(err, json_pretty::Error::TrailingContent { .. }));
+}
+
+#[test]
+fn parse_recovering_from_errors() {
+    let options = ParserOptions {
+        recover: true,
+        ..Default::default()
+    };
+    let (node, diagnostics) = parse_with_diagnostics(
+        r#"{"a": [1 2], "b": tru, "c" [3,], "d": 4"#,
+        TokenizerOptions::default(),
+        options,
+    )
+    .unwrap();
+
+    assert_eq!(to_string(&node), r#"{"a":[1,2],"b":null,"c":[3],"d":4}"#);
+    let columns = diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.error.span().start.column)
+        .collect::<Vec<_>>();
+    assert_eq!(columns, [10, 19, 28, 31, 40]);
+    assert!(diagnostics
+        .iter()
+        .all(|diagnostic| diagnostic.severity == Severity::Error));
+}
+
+#[test]
+fn keep_comments() {
+    let input =
+        "{\n    // The port to listen on\n    \"port\": 8080, // default\n    \"hosts\": []\n}";
+    let (node, comments, diagnostics) =
+        parse_with_comments(input, TokenizerOptions::default(), ParserOptions::default()).unwrap();
+
+    assert!(diagnostics.is_empty());
+    assert_eq!(node.get("port").and_then(Node::as_i64), Some(8080));
+    assert_eq!(
+        comments
+            .get("/port")
+            .map(|attached| attached.after[0].text.as_str()),
+        Some("// default")
+    );
+    assert_eq!(
+        Generator::with_options(&node, &GeneratorOptions::default())
+            .with_comments(&comments)
+            .generate(),
+        input
+    );
+}
+
+#[test]
+fn parse_json5() {
+    let input = "// JSON5\n{unquoted: 'single', hex: 0x10, big: +Infinity, list: [.5,],}";
+    let tokenizer = TokenizerOptions {
+        json5: true,
+        ..Default::default()
+    };
+    let parser = ParserOptions {
+        json5: true,
+        ..Default::default()
+    };
+    let node = parse_with_options(input, tokenizer, parser).unwrap();
+
+    assert_eq!(node.get("unquoted").and_then(Node::as_str), Some("single"));
+    assert_eq!(node.get("hex").and_then(Node::as_i64), Some(16));
+    assert_eq!(
+        to_string(&node),
+        r#"{"unquoted":"single","hex":16,"big":null,"list":[0.5]}"#
+    );
+    assert!(parse(input).is_err());
+}
+
+#[test]
+fn edit_in_place() {
+    let input = "{\r\n  \"name\": \"app\",\r\n  \"version\": \"1.0.0\",  \"files\": [\r\n    \"a\" // main\r\n  ]\r\n}\r\n";
+    let options = TokenizerOptions {
+        comments: true,
+        ..Default::default()
+    };
+    let mut document = cst::parse_with_options(input, options, ParserOptions::default()).unwrap();
+    assert_eq!(document.to_string(), input);
+
+    document
+        .pointer_mut("/version")
+        .unwrap()
+        .set(&Node::String("1.0.1".to_string()))
+        .unwrap();
+    let files = document
+        .pointer_mut("/files")
+        .and_then(cst::Value::as_array_mut);
+    files.unwrap().push(&Node::String("b".to_string())).unwrap();
+
+    assert_eq!(
+        document.to_string(),
+        "{\r\n  \"name\": \"app\",\r\n  \"version\": \"1.0.1\",  \"files\": [\r\n    \"a\", // main\r\n    \"b\"\r\n  ]\r\n}\r\n"
+    );
+    assert!(cst::parse("{\"a\": }").is_err());
+}
+
+#[test]
+fn parse_lossy_surrogates() {
+    let options = TokenizerOptions {
+        lossy_surrogates: true,
+        ..Default::default()
+    };
+    let node = parse_with_options(r#""\ud800""#, options, ParserOptions::default()).unwrap();
+
+    assert_eq!(node.as_str(), Some("\u{FFFD}"));
+}
+
+#[test]
+fn parse_first_and_rest() {
+    let mut tokenizer = Tokenizer::new(r#"{"a": 1} trailing"#);
+    let node = parse_first(&mut tokenizer).unwrap();
+
+    assert_eq!(node.get("a").and_then(Node::as_i64), Some(1));
+    assert_eq!(tokenizer.rest(), " trailing");
+}
+
+#[test]
+fn format_pretty() {
+    let node = parse(r#"{"a":[1,"x<y"]}"#).unwrap();
+    let options = GeneratorOptions {
+        indent: "  ".to_string(),
+        escape: EscapeOptions {
+            html_safe: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    assert_eq!(
+        to_string_pretty(&node, &options),
+        "{\n  \"a\": [\n    1,\n    \"x\\u003cy\"\n  ]\n}"
+    );
+}
+
+#[test]
+fn format_minified() {
+    let node = parse("{\n  \"a\": [1, 2],\n  \"b\": {}\n}").unwrap();
+
+    assert_eq!(to_string(&node), r#"{"a":[1,2],"b":{}}"#);
+}
+
+#[test]
+fn format_into_writer() {
+    let node = parse(r#"{"a": [1, "x"]}"#).unwrap();
+    let options = GeneratorOptions {
+        final_newline: true,
+        ..Default::default()
+    };
+
+    let mut bytes = Vec::new();
+    to_writer(&mut bytes, &node, &options).unwrap();
+
+    assert_eq!(bytes, to_string_pretty(&node, &options).into_bytes());
+}
+
+#[test]
+fn parse_borrowed_and_convert() {
+    let input = r#"{"name": "json-pretty", "tags": ["a\tb"]}"#;
+    let node = parse_borrowed(input).unwrap();
+
+    assert_eq!(
+        node.get("name").and_then(|n| n.as_str()),
+        Some("json-pretty")
+    );
+    assert_eq!(
+        node.get("tags")
+            .and_then(|tags| tags.get_index(0))
+            .and_then(|n| n.as_str()),
+        Some("a\tb")
+    );
+    assert_eq!(node.into_owned(), parse(input).unwrap());
+}
+
+#[test]
+fn pull_selected_fields() {
+    let input = r#"[
+        {"id": 1, "payload": {"big": [1, 2, 3]}, "name": "a"},
+        {"payload": [], "id": 2, "name": "b"}
+    ]"#;
+    let mut reader = Reader::new(input);
+    let mut ids = Vec::new();
+
+    while let Some(event) = reader.next_event().unwrap() {
+        match event {
+            Event::Key(key) if key == "id" => {
+                if let Some(Event::Scalar(Scalar::Number(id))) = reader.next_event().unwrap() {
+                    ids.push(id.into_owned());
+                }
+            }
+            Event::Key(_) => reader.skip_value().unwrap(),
+            _ => {}
+        }
+    }
+
+    assert_eq!(ids, ["1", "2"]);
+}
+
+#[test]
+fn visit_without_building_a_tree() {
+    // Sums up every number in the text.
+    struct Sum(f64);
+
+    impl Visitor<'_> for Sum {
+        type Error = Error;
+
+        fn number(&mut self, num: Cow<str>) -> Result<(), Error> {
+            self.0 += num.parse::<f64>().unwrap();
+            Ok(())
+        }
+    }
+
+    let mut sum = Sum(0.0);
+    visitor::visit(
+        Tokenizer::new(r#"{"a": [1, 2.5], "b": {"c": -0.5}}"#),
+        &mut sum,
+    )
+    .unwrap();
+    assert_eq!(sum.0, 3.0);
+}
@@ -0,0 +1,425 @@
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+fn json_pretty(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_json-pretty"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+// Creates a fresh directory for a test under the system temporary directory.
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("json-pretty-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8(output.stderr.clone()).unwrap()
+}
+
+#[test]
+fn format_stdin() {
+    let output = json_pretty(&[], r#"{"a":1}"#);
+
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "{\n    \"a\": 1\n}\n");
+
+    let output = json_pretty(&["-"], "[true]");
+    assert_eq!(stdout(&output), "[\n    true\n]\n");
+}
+
+#[test]
+fn format_text() {
+    let output = json_pretty(&["--text", "[1]", "-n", "2"], "");
+
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "[\n  1\n]\n");
+}
+
+#[test]
+fn format_compact_and_minified() {
+    let input = "{\"a\": [1, 2], \"b\": null}";
+
+    let output = json_pretty(&["--compact"], input);
+    assert_eq!(stdout(&output), "{\"a\": [1, 2], \"b\": null}\n");
+
+    let output = json_pretty(&["--minify"], input);
+    assert_eq!(stdout(&output), "{\"a\":[1,2],\"b\":null}\n");
+}
+
+#[test]
+fn format_with_tabs_and_crlf() {
+    let output = json_pretty(&["--tabs", "--crlf"], "{\"a\": [1]}");
+    assert_eq!(stdout(&output), "{\r\n\t\"a\": [\r\n\t\t1\r\n\t]\r\n}\r\n");
+
+    let output = json_pretty(&["--indent", "..", "--no-final-newline"], "[1]");
+    assert_eq!(stdout(&output), "[\n..1\n]");
+}
+
+#[test]
+fn format_files_with_labels() {
+    let dir = temp_dir("labels");
+    let good = dir.join("good.json");
+    let bad = dir.join("bad.json");
+    fs::write(&good, "[1]").unwrap();
+    fs::write(&bad, "[1,\n 2 3]").unwrap();
+
+    let output = json_pretty(&[good.to_str().unwrap(), bad.to_str().unwrap()], "");
+
+    assert_eq!(output.status.code(), Some(11));
+    assert_eq!(
+        stdout(&output),
+        format!("==> {} <==\n[\n    1\n]\n", good.display())
+    );
+    assert!(stderr(&output).contains(&format!("--> {}:2:4", bad.display())));
+    assert!(stderr(&output).contains("2 |  2 3]\n  |    ^"));
+}
+
+#[test]
+fn stream_stdin() {
+    let output = json_pretty(
+        &["--stream", "--inline-arrays", "20"],
+        r#"{"a":[1,2],"b":{}}"#,
+    );
+
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "{\n    \"a\": [1, 2],\n    \"b\": {}\n}\n");
+
+    let output = json_pretty(&["--stream"], "[1,\n 2 3]");
+    assert_eq!(output.status.code(), Some(11));
+    assert_eq!(stdout(&output), "[\n    1,\n    2\n");
+    assert!(stderr(&output).contains("--> <stdin>:2:4"));
+}
+
+#[test]
+fn reject_deep_nesting() {
+    let output = json_pretty(&[], &"[".repeat(100_000));
+    assert_eq!(output.status.code(), Some(16));
+    assert!(stderr(&output).contains("exceeds the limit of 128"));
+
+    let output = json_pretty(&["--stream"], &"{\"a\": ".repeat(1000));
+    assert_eq!(output.status.code(), Some(16));
+
+    let input = format!("{}{}", "[".repeat(200), "]".repeat(200));
+    let output = json_pretty(&["--max-depth", "200", "--minify"], &input);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), format!("{}\n", input));
+
+    let input = format!("{}{}", "[".repeat(100_000), "]".repeat(100_000));
+    let output = json_pretty(&["--max-depth", "100000", "--minify"], &input);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), format!("{}\n", input));
+}
+
+#[test]
+fn report_duplicate_keys() {
+    let input = "{\"a\": 1,\n \"a\": 2}";
+
+    let output = json_pretty(&["--minify"], input);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "{\"a\":2}\n");
+    assert!(stderr(&output).contains(
+        "warning: The key \"a\" is duplicated; it first appears at line 1, column 2.\n --> <stdin>:2:2"
+    ));
+
+    // Every duplicate is reported, also after the first one.
+    let output = json_pretty(
+        &["--duplicate-keys", "error"],
+        "{\"a\": 1, \"a\": 2, \"b\": [}, \"a\": 3}",
+    );
+    assert_eq!(output.status.code(), Some(19));
+    assert_eq!(stdout(&output), "");
+    assert_eq!(stderr(&output).matches("error: The key \"a\"").count(), 2);
+    assert_eq!(stderr(&output).matches("error: Expected").count(), 1);
+
+    for policy in ["first", "last", "all"] {
+        let output = json_pretty(&["--duplicate-keys", policy, "--minify"], input);
+        assert!(output.status.success());
+        assert!(stderr(&output).contains("warning: The key \"a\" is duplicated"));
+    }
+    let output = json_pretty(&["--duplicate-keys", "all", "--minify"], input);
+    assert_eq!(stdout(&output), "{\"a\":1,\"a\":2}\n");
+}
+
+#[test]
+fn stream_duplicate_keys() {
+    let input = "{\"a\": 1,\n \"a\": 2}";
+
+    let output = json_pretty(&["--stream", "--minify"], input);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "{\"a\":1,\"a\":2}\n");
+
+    let output = json_pretty(&["--stream", "--duplicate-keys", "error"], input);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).contains("cannot be used with '--duplicate-keys <POLICY>'"));
+}
+
+#[test]
+fn report_every_error() {
+    let output = json_pretty(
+        &["--duplicate-keys", "warn"],
+        "{\"a\": [1 2],\n \"a\": 01,\n \"b\": 3",
+    );
+
+    assert_eq!(output.status.code(), Some(11));
+    assert_eq!(stdout(&output), "");
+    let stderr = stderr(&output);
+    let reported = stderr
+        .lines()
+        .filter(|line| line.starts_with("error:") || line.starts_with("warning:"))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        reported,
+        [
+            "error: Expected ',', but found number 2.",
+            "warning: The key \"a\" is duplicated; it first appears at line 1, column 2.",
+            "error: Invalid number: leading zeros are not allowed.",
+            "error: Expected ',', but reached EOF.",
+        ]
+    );
+    assert!(stderr.contains("--> <stdin>:3:8"));
+}
+
+#[test]
+fn report_trailing_content() {
+    for args in [&[][..], &["--stream"]] {
+        let output = json_pretty(args, "{\"a\": 1} xyz");
+        assert_eq!(output.status.code(), Some(15));
+        assert!(stderr(&output).contains("after the end of the JSON text"));
+        assert!(stderr(&output).contains("--> <stdin>:1:10"));
+    }
+}
+
+#[test]
+fn shorten_long_source_lines() {
+    let input = format!("[{}@, {}2]", "1, ".repeat(1000), "2, ".repeat(1000));
+    let output = json_pretty(&[], &input);
+    assert_eq!(output.status.code(), Some(10));
+
+    // Sixty characters on either side of the error.
+    let context = format!("{}@, {}", "1, ".repeat(20), "2, ".repeat(19));
+    let expected = format!("1 | ...{}...\n  |    {}^\n", context, " ".repeat(60));
+    assert!(stderr(&output).ends_with(&expected));
+}
+
+#[test]
+fn format_jsonc() {
+    let input = "// settings\n{\"a\": 1, /* one */ \"b\": [\n// none\n]}";
+    let expected = "// settings\n{\n  \"a\": 1, /* one */\n  \"b\": [\n    // none\n  ]\n}\n";
+
+    let output = json_pretty(&["--jsonc", "-n", "2"], input);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), expected);
+
+    let output = json_pretty(&["--jsonc", "-n", "2", "--stream"], input);
+    assert_eq!(stdout(&output), expected);
+
+    let output = json_pretty(&["--jsonc", "--minify"], input);
+    assert_eq!(stdout(&output), "{\"a\":1,\"b\":[]}\n");
+
+    let dir = temp_dir("jsonc-write");
+    let path = dir.join("a.json");
+    fs::write(&path, input).unwrap();
+    let output = json_pretty(
+        &["--jsonc", "--minify", "--write", path.to_str().unwrap()],
+        "",
+    );
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).contains("would drop the comments"));
+    assert_eq!(fs::read_to_string(&path).unwrap(), input);
+
+    let output = json_pretty(&[], input);
+    assert_eq!(output.status.code(), Some(10));
+}
+
+#[test]
+fn format_jsonc_duplicate_keys() {
+    let input = "{\n // one\n \"a\": 1,\n \"a\": 2, // two\n \"a\": [3 /* three */]\n}";
+    let expected =
+        "{\n  // one\n  \"a\": 1,\n  \"a\": 2, // two\n  \"a\": [\n    3 /* three */\n  ]\n}\n";
+    let args = ["--jsonc", "-n", "2", "--duplicate-keys", "all"];
+
+    let output = json_pretty(&args, input);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), expected);
+
+    let output = json_pretty(&args, expected);
+    assert_eq!(stdout(&output), expected);
+}
+
+#[test]
+fn format_json5() {
+    let input = "// JSON5\n{a: 'it\\'s', b: [0x1F, .5, NaN,],}";
+    let expected = "{\n  \"a\": \"it's\",\n  \"b\": [\n    31,\n    0.5,\n    null\n  ]\n}\n";
+
+    let output = json_pretty(&["--json5", "-n", "2"], input);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), expected);
+    assert!(stderr(&output).contains("warning: NaN cannot be written as JSON and becomes null."));
+
+    let output = json_pretty(&["--json5", "-n", "2", "--stream"], input);
+    assert_eq!(stdout(&output), expected);
+
+    let output = json_pretty(&["--json5", "--jsonc", "-n", "2"], input);
+    assert_eq!(stdout(&output), format!("// JSON5\n{}", expected));
+
+    let output = json_pretty(&[], input);
+    assert_eq!(output.status.code(), Some(10));
+
+    let output = json_pretty(
+        &["--json5", "--minify"],
+        "{null: 1, true: 2, false: 3, Infinity: 4, NaN: 5}",
+    );
+    assert_eq!(
+        stdout(&output),
+        "{\"null\":1,\"true\":2,\"false\":3,\"Infinity\":4,\"NaN\":5}\n"
+    );
+}
+
+#[test]
+fn allow_trailing_commas() {
+    let input = "[1, 2,]";
+
+    let output = json_pretty(
+        &["--allow-trailing-commas", "--trailing-commas", "-n", "2"],
+        input,
+    );
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "[\n  1,\n  2,\n]\n");
+    assert!(stderr(&output).contains("warning: Found a comma after the last member or element."));
+
+    let output = json_pretty(&["--minify"], input);
+    assert_eq!(output.status.code(), Some(11));
+}
+
+#[test]
+fn warnings_do_not_set_the_exit_status() {
+    let output = json_pretty(&["--allow-trailing-commas"], "{\"a\": [1,], \"a\": 2,}");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stderr(&output).matches("warning:").count(), 3);
+
+    let output = json_pretty(&["--json5"], "[NaN, Infinity]");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stderr(&output).matches("warning:").count(), 2);
+}
+
+#[test]
+fn report_missing_file() {
+    let output = json_pretty(&["/nonexistent/file.json"], "");
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("/nonexistent/file.json"));
+}
+
+#[test]
+fn report_invalid_utf8_in_file() {
+    let dir = temp_dir("utf8");
+    let file = dir.join("latin1.json");
+    fs::write(&file, b"[\"caf\xe9\"]").unwrap();
+
+    let output = json_pretty(&[file.to_str().unwrap()], "");
+    assert_eq!(output.status.code(), Some(18));
+    assert!(stderr(&output).contains("latin1.json:1:6"));
+}
+
+#[test]
+fn check_lists_unformatted_files() {
+    let dir = temp_dir("check");
+    let formatted = dir.join("formatted.json");
+    let unformatted = dir.join("unformatted.json");
+    fs::write(&formatted, "[\n    1\n]\n").unwrap();
+    fs::write(&unformatted, "[1]").unwrap();
+
+    let output = json_pretty(
+        &[
+            "--check",
+            formatted.to_str().unwrap(),
+            unformatted.to_str().unwrap(),
+        ],
+        "",
+    );
+
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(stdout(&output), format!("{}\n", unformatted.display()));
+
+    let output = json_pretty(&["--check", formatted.to_str().unwrap()], "");
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "");
+}
+
+#[test]
+fn diff_prints_unified_diff() {
+    let output = json_pretty(&["--diff"], "[1]\n");
+
+    assert!(output.status.success());
+    assert_eq!(
+        stdout(&output),
+        "--- a/<stdin>\n+++ b/<stdin>\n@@ -1 +1,3 @@\n-[1]\n+[\n+    1\n+]\n"
+    );
+}
+
+#[test]
+fn diff_large_minified_input() {
+    let objects = (0..20_000)
+        .map(|i| format!("{{\"id\":{}}}", i))
+        .collect::<Vec<_>>();
+    let input = format!("[{}]", objects.join(","));
+
+    let output = json_pretty(&["--diff"], &input);
+
+    assert!(output.status.success());
+    let stdout = stdout(&output);
+    assert!(stdout.starts_with("--- a/<stdin>\n+++ b/<stdin>\n@@ -1 +1,60002 @@\n-[{\"id\":0},"));
+    assert!(stdout.ends_with("+    {\n+        \"id\": 19999\n+    }\n+]\n"));
+}
+
+#[cfg(unix)]
+#[test]
+fn write_rewrites_files_in_place() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = temp_dir("write");
+    let file = dir.join("data.json");
+    fs::write(&file, r#"{"a":[1,2]}"#).unwrap();
+    fs::set_permissions(&file, fs::Permissions::from_mode(0o640)).unwrap();
+
+    let output = json_pretty(&["--write", file.to_str().unwrap()], "");
+
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "");
+    assert_eq!(
+        fs::read_to_string(&file).unwrap(),
+        "{\n    \"a\": [\n        1,\n        2\n    ]\n}\n"
+    );
+    assert_eq!(
+        fs::metadata(&file).unwrap().permissions().mode() & 0o777,
+        0o640
+    );
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+}
+
+#[test]
+fn write_conflicts_with_check() {
+    let output = json_pretty(&["--write", "--check", "a.json"], "");
+
+    assert_eq!(output.status.code(), Some(2));
+}